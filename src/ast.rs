@@ -1,27 +1,62 @@
 use std::{collections::HashMap, fmt::Display};
 
+use crate::span::Span;
 use crate::token::Token;
 
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum Ast {
     Expression(Expression),
     Statement(Statement),
 }
+
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+    /// filled in by the type checker
+    pub tp: Option<Type>,
+}
 #[derive(Debug, Clone)]
-pub enum Expression {
+pub enum ExpressionKind {
     Value(Value),
     BinaryOperation(Box<Expression>, Bop, Box<Expression>),
     UnaryOperation(Uop, Box<Expression>),
-    If(If)
+    If(If),
+    While(While),
+    For(For),
+    /// `value.field`
+    Field(Box<Expression>, String),
+    Match(Match),
+    /// `start..end`, or `start..=end` when inclusive
+    Range(Box<Expression>, Box<Expression>, bool),
+    /// `value as tp`
+    Cast(Box<Expression>, Type),
+    /// `string[index]`, the char at that index
+    Index(Box<Expression>, Box<Expression>),
+    /// `{ ... }`, which has the value of the expression it ends in
+    Block(Vec<Ast>),
 }
 
 #[derive(Debug, Clone)]
-pub enum Statement {
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+#[derive(Debug, Clone)]
+pub enum StatementKind {
     Function(Function),
     Struct(Struct),
     Enum(Enum),
     Let(Let),
+    Assign(Assign),
+    /// an expression followed by a semicolon, its value is discarded
+    Expression(Expression),
+    Return(Option<Expression>),
+    /// `break` or `continue`, with the label of the loop they refer to
+    Break(Option<String>),
+    Continue(Option<String>),
     Import(Import),
 }
 
@@ -32,32 +67,70 @@ pub struct Import {
 #[derive(Debug, Clone)]
 pub struct Let {
     pub name: String,
+    pub mutable: bool,
+    /// filled in by the type checker when it isn't written out
     pub tp: Type,
-    /// possibly not nessesary
     pub type_infered: bool,
+    /// `None` for `let x;`, which is given its value by a later assignment
+    pub value: Option<Expression>,
+}
+/// `x = value`, or `x += value` etc. when `op` is set. the target is a
+/// variable or a field of one, like `p.x`
+#[derive(Debug, Clone)]
+pub struct Assign {
+    pub target: Expression,
+    pub op: Option<Bop>,
     pub value: Expression,
 }
-// todo
 #[derive(Debug, Clone)]
 pub struct Enum {
+    pub name: String,
+    pub variants: Vec<Variant>,
+    /// the `///` comments before it, one line each
+    pub doc: Option<String>,
+}
+/// a variant of an enum. tuple variants name their fields `0`, `1`...
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: String,
+    pub span: Span,
+    pub shape: VariantShape,
+    pub fields: Vec<Arg>,
+    pub doc: Option<String>,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariantShape {
+    /// `Empty`
+    Unit,
+    /// `Circle(f32)`
+    Tuple,
+    /// `Rect { w: i32, h: i32 }`
+    Named,
 }
 #[derive(Debug, Clone)]
 pub struct Struct {
     pub name: String,
-    pub fields: Vec<Arg>
+    pub fields: Vec<Arg>,
+    pub doc: Option<String>,
 }
 #[derive(Debug, Clone)]
 pub struct Function {
     pub name: String,
     pub args: Vec<Arg>,
     pub ret_tp: Type,
-    pub body: Vec<Ast>
+    /// the return type, or the closing parenthesis of the params when there is none
+    pub ret_span: Span,
+    pub body: Vec<Ast>,
+    pub doc: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Arg {
     pub name: String,
     pub tp: Type,
+    pub span: Span,
+    /// only struct and variant fields can be documented
+    pub doc: Option<String>,
 }
 
 // todo
@@ -66,6 +139,56 @@ pub struct Type {
     pub name: String,
 }
 
+impl Type {
+    pub fn new<T: ToString>(name: T) -> Type {
+        Type { name: name.to_string() }
+    }
+    pub fn is_integer(&self) -> bool {
+        matches!(self.name.as_str(), "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64")
+    }
+    pub fn is_bool(&self) -> bool {
+        self.name == "bool"
+    }
+    /// a unicode scalar value, held in an i32
+    pub fn is_char(&self) -> bool {
+        self.name == "char"
+    }
+    pub fn is_signed(&self) -> bool {
+        matches!(self.name.as_str(), "i8" | "i16" | "i32" | "i64")
+    }
+    pub fn is_float(&self) -> bool {
+        matches!(self.name.as_str(), "f32" | "f64")
+    }
+    /// size of a number type in bits, 32 for everything else
+    pub fn bits(&self) -> u32 {
+        match self.name.as_str() {
+            "i8" | "u8" => 8,
+            "i16" | "u16" => 16,
+            "i64" | "u64" | "f64" => 64,
+            _ => 32,
+        }
+    }
+    /// the smallest and largest value of an integer type
+    pub fn int_range(&self) -> (i128, i128) {
+        let bits = self.bits();
+        if self.is_signed() {
+            (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+        } else {
+            (0, (1 << bits) - 1)
+        }
+    }
+    /// the wasm value type this type is represented by. anything that isn't a
+    /// number lives in memory and is passed around as an i32 pointer
+    pub fn wasm_type(&self) -> &'static str {
+        match self.name.as_str() {
+            "i64" | "u64" => "i64",
+            "f32" => "f32",
+            "f64" => "f64",
+            _ => "i32",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TypeConstr {
     pub name: String,
@@ -79,10 +202,10 @@ pub enum Constant {
 }
 
 impl Constant {
-    pub fn get_number(&self) -> Option<i32> {
+    pub fn get_number(&self) -> Option<i128> {
         match self {
             Constant::Value(v) => match v {
-                Value::I32(i) => Some(*i),
+                Value::Int(i, _) => Some(*i),
                 _ => None
             },
             Constant::Arr(_) => None,
@@ -92,11 +215,70 @@ impl Constant {
 
 #[derive(Debug, Clone)]
 pub enum Value {
-    F32(f32),
-    I32(i32),
+    Bool(bool),
+    Char(char),
+    /// a number literal and the type its suffix gives it, if it has one
+    Float(f64, Option<Type>),
+    /// wide enough for every `i64` and `u64`, the type checker makes sure it fits its type
+    Int(i128, Option<Type>),
     TypeConstr(TypeConstr),
     Var(String),
     FnCall(FnCall),
+    StructLit(StructLit),
+    EnumLit(EnumLit),
+}
+
+/// `Point { x: 1, y: 2 }`
+#[derive(Debug, Clone)]
+pub struct StructLit {
+    pub name: String,
+    /// the fields in the order they are written, with the span of their name
+    pub fields: Vec<(String, Span, Expression)>,
+}
+
+/// `Shape::Circle(1.0)`, `Shape::Rect { w: 1, h: 2 }` or `Shape::Empty`
+#[derive(Debug, Clone)]
+pub struct EnumLit {
+    pub name: String,
+    pub variant: String,
+    pub shape: VariantShape,
+    /// the fields in the order they are written, tuple fields are named `0`, `1`...
+    pub fields: Vec<(String, Span, Expression)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub value: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Vec<Ast>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum PatternKind {
+    /// `_`
+    Wildcard,
+    /// binds the whole value to a name
+    Binding(String),
+    /// `Shape::Rect { w, h: height, .. }`. tuple fields are named `0`, `1`...
+    Variant {
+        name: String,
+        variant: String,
+        shape: VariantShape,
+        fields: Vec<(String, Pattern)>,
+        /// whether the pattern ends in `..`
+        rest: bool,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -111,16 +293,30 @@ pub enum Bop {
     Minus,
     Slash,
     Star,
+    Rem,
+    Shl,
+    Shr,
     Eq,
+    NotEq,
     Gt,
     Lt,
     GtEq,
     LtEq,
     Carret,
+    /// `&&`, which only evaluates its rhs when the lhs is true
+    And,
+    /// `||`, which only evaluates its rhs when the lhs is false
+    Or,
 }
 
 #[derive(Debug, Clone)]
 pub enum Uop {
+    /// `-x`
+    Neg,
+    /// `!x`
+    Not,
+    /// `~x`
+    BitNot,
 }
 
 #[derive(Debug, Clone)]
@@ -131,34 +327,167 @@ pub struct If {
     pub els: Option<Vec<Ast>>,
 }
 
+#[derive(Debug, Clone)]
+pub struct While {
+    pub label: Option<String>,
+    pub condition: Box<Expression>,
+    pub block: Vec<Ast>,
+}
+
+#[derive(Debug, Clone)]
+pub struct For {
+    pub label: Option<String>,
+    pub var: String,
+    pub var_span: Span,
+    pub iter: Box<Expression>,
+    pub block: Vec<Ast>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ElseIf {
     pub condition: Box<Expression>,
     pub block: Vec<Ast>,
 }
 
+impl Ast {
+    pub fn span(&self) -> Span {
+        match self {
+            Ast::Expression(e) => e.span,
+            Ast::Statement(s) => s.span,
+        }
+    }
+}
+
+impl Expression {
+    /// the type the type checker gave the expression
+    pub fn tp(&self) -> &Type {
+        self.tp.as_ref().expect("expressions are typed before they are compiled")
+    }
+}
+
+impl Pattern {
+    /// the names the pattern binds
+    pub fn bindings(&self) -> Vec<String> {
+        match &self.kind {
+            PatternKind::Wildcard => Vec::new(),
+            PatternKind::Binding(name) => vec![name.clone()],
+            PatternKind::Variant { fields, .. } => fields.iter().flat_map(|(_, p)| p.bindings()).collect(),
+        }
+    }
+}
+
+impl ExpressionKind {
+    /// `as` binds like a unary operator, so `-x as u8` is `(-x) as u8` and
+    /// `a * b as f32` is `a * (b as f32)`
+    pub const CAST_PRECEDENCE: u8 = Uop::PRECEDENCE;
+
+    /// expressions ending in a block, which don't need a `;` to be used as a statement
+    pub fn is_block_like(&self) -> bool {
+        matches!(self,
+            ExpressionKind::If(_) | ExpressionKind::While(_) | ExpressionKind::For(_) | ExpressionKind::Match(_) | ExpressionKind::Block(_)
+        )
+    }
+}
+
+impl Uop {
+    /// unary operators bind tighter than every binary operator except `^`,
+    /// so `-2 ^ 2` is `-(2 ^ 2)`
+    pub const PRECEDENCE: u8 = 7;
+
+    pub fn from_token(token: &Token) -> Option<Uop> {
+        match token {
+            Token::Minus => Some(Uop::Neg),
+            Token::Not => Some(Uop::Not),
+            Token::Tilde => Some(Uop::BitNot),
+            _ => None,
+        }
+    }
+}
+
 impl Bop {
+    /// how tightly the operator binds, higher binds tighter
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Bop::Or => 1,
+            Bop::And => 2,
+            Bop::Eq | Bop::NotEq | Bop::Gt | Bop::Lt | Bop::GtEq | Bop::LtEq => 3,
+            Bop::Shl | Bop::Shr => 4,
+            Bop::Plus | Bop::Minus => 5,
+            Bop::Star | Bop::Slash | Bop::Rem => 6,
+            Bop::Carret => 8,
+        }
+    }
+    pub fn is_right_assoc(&self) -> bool {
+        matches!(self, Bop::Carret)
+    }
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, Bop::Eq | Bop::NotEq | Bop::Gt | Bop::Lt | Bop::GtEq | Bop::LtEq)
+    }
+    pub fn is_logical(&self) -> bool {
+        matches!(self, Bop::And | Bop::Or)
+    }
     pub fn from_token(token: Token) -> Option<Bop> {
         match token {
             Token::Plus => Some(Bop::Plus),
             Token::Minus => Some(Bop::Minus),
             Token::Slash => Some(Bop::Slash),
             Token::Star => Some(Bop::Star),
+            Token::Percent => Some(Bop::Rem),
+            Token::Shl => Some(Bop::Shl),
+            Token::Shr => Some(Bop::Shr),
+            Token::EqTo => Some(Bop::Eq),
+            Token::NotEq => Some(Bop::NotEq),
             Token::Gt => Some(Bop::Gt),
             Token::Lt => Some(Bop::Lt),
             Token::GtEq => Some(Bop::GtEq),
             Token::LtEq => Some(Bop::LtEq),
             Token::Carret => Some(Bop::Carret),
+            Token::AndAnd => Some(Bop::And),
+            Token::OrOr => Some(Bop::Or),
             _ => None,
         }
     }
 }
 
+impl Display for Bop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Bop::Plus => "+",
+            Bop::Minus => "-",
+            Bop::Slash => "/",
+            Bop::Star => "*",
+            Bop::Rem => "%",
+            Bop::Shl => "<<",
+            Bop::Shr => ">>",
+            Bop::Eq => "==",
+            Bop::NotEq => "!=",
+            Bop::Gt => ">",
+            Bop::Lt => "<",
+            Bop::GtEq => ">=",
+            Bop::LtEq => "<=",
+            Bop::Carret => "^",
+            Bop::And => "&&",
+            Bop::Or => "||",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Display for Uop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Uop::Neg => write!(f, "-"),
+            Uop::Not => write!(f, "!"),
+            Uop::BitNot => write!(f, "~"),
+        }
+    }
+}
+
 impl Display for ElseIf {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "else if {} then\n", self.condition)?;
+        writeln!(f, "else if {} then", self.condition)?;
         for ast in &self.block {
-            write!(f, "{}\n", ast)?;
+            writeln!(f, "{}", ast)?;
         }
         Ok(())
     }
@@ -170,67 +499,202 @@ impl Display for If {
     // pub elsifs: Option<Vec<ElseIf>>,
     // pub els: Option<Vec<Ast>>,
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "if {} then\n", self.condition)?;
+        writeln!(f, "if {} then", self.condition)?;
         for ast in &self.block {
-            write!(f, "{}\n", ast)?;
+            writeln!(f, "{}", ast)?;
         }
-        for elsif in self.elsifs.clone().unwrap_or(Vec::new()) {
+        for elsif in self.elsifs.clone().unwrap_or_default() {
             write!(f, "{}", elsif)?;
         }
-        write!(f, "else\n")?;
-        match &self.els {
-            Some(b) => {
-                for ast in b {
-                    write!(f, "{}\n", ast)?;
-                };
-            },
-            None => {}
+        writeln!(f, "else")?;
+        if let Some(b) = &self.els {
+            for ast in b {
+                writeln!(f, "{}", ast)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for While {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = &self.label {
+            write!(f, "'{}: ", label)?;
+        }
+        writeln!(f, "while {} do", self.condition)?;
+        for ast in &self.block {
+            writeln!(f, "{}", ast)?;
         }
         Ok(())
     }
 }
 
+impl Display for For {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = &self.label {
+            write!(f, "'{}: ", label)?;
+        }
+        writeln!(f, "for {} in {} do", self.var, self.iter)?;
+        for ast in &self.block {
+            writeln!(f, "{}", ast)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Match {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "match {} {{", self.value)?;
+        for arm in &self.arms {
+            writeln!(f, "{} =>", arm.pattern)?;
+            for ast in &arm.body {
+                writeln!(f, "{}", ast)?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            PatternKind::Wildcard => write!(f, "_"),
+            PatternKind::Binding(name) => write!(f, "{}", name),
+            PatternKind::Variant { name, variant, shape, fields, rest } => {
+                write!(f, "{}::{}", name, variant)?;
+                let mut fields = fields.iter().map(|(n, p)| match shape {
+                    VariantShape::Named => format!("{}: {}", n, p),
+                    _ => p.to_string(),
+                }).collect::<Vec<_>>();
+                if *rest {
+                    fields.push(String::from(".."));
+                }
+                match shape {
+                    VariantShape::Unit => Ok(()),
+                    VariantShape::Tuple => write!(f, "({})", fields.join(", ")),
+                    VariantShape::Named => write!(f, " {{ {} }}", fields.join(", ")),
+                }
+            }
+        }
+    }
+}
+
+fn write_doc(f: &mut std::fmt::Formatter<'_>, doc: &Option<String>) -> std::fmt::Result {
+    for line in doc.iter().flat_map(|d| d.lines()) {
+        writeln!(f, "/// {}", line)?;
+    }
+    Ok(())
+}
+impl Display for Enum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_doc(f, &self.doc)?;
+        writeln!(f, "enum {} {{", self.name)?;
+        for variant in &self.variants {
+            write_doc(f, &variant.doc)?;
+            let fields = variant.fields.iter().map(|a| match variant.shape {
+                VariantShape::Named => format!("{}: {}", a.name, a.tp.name),
+                _ => a.tp.name.clone(),
+            }).collect::<Vec<_>>();
+            match variant.shape {
+                VariantShape::Unit => writeln!(f, "{},", variant.name)?,
+                VariantShape::Tuple => writeln!(f, "{}({}),", variant.name, fields.join(", "))?,
+                VariantShape::Named => writeln!(f, "{} {{ {} }},", variant.name, fields.join(", "))?,
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
 impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Expression::Value(v) => write!(f, "{:?}", v),
-            Expression::BinaryOperation(lhs, op, rhs) => write!(f, "{} {:?} {}", lhs, op, rhs),
-            Expression::UnaryOperation(_, _) => todo!(),
-            Expression::If(i) => write!(f, "{}", i),
+        match &self.kind {
+            ExpressionKind::Value(v) => write!(f, "{:?}", v),
+            ExpressionKind::BinaryOperation(lhs, op, rhs) => write!(f, "({} {:?} {})", lhs, op, rhs),
+            ExpressionKind::UnaryOperation(op, expr) => write!(f, "({:?} {})", op, expr),
+            ExpressionKind::If(i) => write!(f, "{}", i),
+            ExpressionKind::While(w) => write!(f, "{}", w),
+            ExpressionKind::For(l) => write!(f, "{}", l),
+            ExpressionKind::Field(value, field) => write!(f, "{}.{}", value, field),
+            ExpressionKind::Match(m) => write!(f, "{}", m),
+            ExpressionKind::Range(start, end, inclusive) => {
+                write!(f, "({}{}{})", start, if *inclusive { "..=" } else { ".." }, end)
+            }
+            ExpressionKind::Cast(value, tp) => write!(f, "({} as {})", value, tp.name),
+            ExpressionKind::Index(value, index) => write!(f, "{}[{}]", value, index),
+            ExpressionKind::Block(block) => {
+                writeln!(f, "{{")?;
+                for ast in block {
+                    writeln!(f, "{}", ast)?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
 impl Display for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_doc(f, &self.doc)?;
         write!(f, "fn {}(", self.name)?;
         for arg in &self.args {
             write!(f, "{:?}", arg)?;
         }
-        write!(f, "): {:?}\n", self.ret_tp)?;
+        writeln!(f, "): {:?}", self.ret_tp)?;
         for ast in &self.body {
-            write!(f, "{}\n", ast)?;
+            writeln!(f, "{}", ast)?;
         }
         Ok(())
     }
 }
 impl Display for Struct {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "struct {} {{", self.name)?;
+        write_doc(f, &self.doc)?;
+        writeln!(f, "struct {} {{", self.name)?;
         for arg in &self.fields {
-            write!(f, "{:?}\n", arg)?;
+            write_doc(f, &arg.doc)?;
+            writeln!(f, "{}: {},", arg.name, arg.tp.name)?;
         }
-        write!(f, "}}\n")?;
+        writeln!(f, "}}")?;
         Ok(())
     }
 }
+impl Display for Let {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "let ")?;
+        if self.mutable {
+            write!(f, "mut ")?;
+        }
+        write!(f, "{}", self.name)?;
+        if !self.type_infered {
+            write!(f, ": {}", self.tp.name)?;
+        }
+        match &self.value {
+            Some(value) => write!(f, " = {};", value),
+            None => write!(f, ";"),
+        }
+    }
+}
+impl Display for Assign {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.op {
+            Some(op) => write!(f, "{} {}= {};", self.target, op, self.value),
+            None => write!(f, "{} = {};", self.target, self.value),
+        }
+    }
+}
 impl Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Statement::Function(func) => write!(f, "{}", func),
-            Statement::Struct(sct) => write!(f, "{}", sct),
-            Statement::Enum(_) => todo!(),
-            Statement::Let(_) => todo!(),
-            Statement::Import(i) => write!(f, "{}", i.path),
+        match &self.kind {
+            StatementKind::Function(func) => write!(f, "{}", func),
+            StatementKind::Struct(sct) => write!(f, "{}", sct),
+            StatementKind::Enum(e) => write!(f, "{}", e),
+            StatementKind::Let(l) => write!(f, "{}", l),
+            StatementKind::Assign(a) => write!(f, "{}", a),
+            StatementKind::Expression(e) => write!(f, "{};", e),
+            StatementKind::Return(Some(e)) => write!(f, "return {};", e),
+            StatementKind::Return(None) => write!(f, "return;"),
+            StatementKind::Break(label) => write!(f, "break{};", label.as_ref().map(|l| format!(" '{}", l)).unwrap_or_default()),
+            StatementKind::Continue(label) => write!(f, "continue{};", label.as_ref().map(|l| format!(" '{}", l)).unwrap_or_default()),
+            StatementKind::Import(i) => write!(f, "{}", i.path),
         }
     }
 }
@@ -1,11 +1,14 @@
-use std::{collections::HashMap, error::Error, fmt::Display, fs, ops::Deref};
+use std::{collections::{BTreeMap, HashMap}, fs};
 
-use crate::ast::{Ast, Bop, Constant, ElseIf, Expression, Function, If, Import, TypeConstr, Value};
+use crate::ast::{Arg, Ast, Bop, Constant, Enum, EnumLit, Expression, ExpressionKind, Function, If, Import, Let, Assign, Match, MatchArm, Pattern, PatternKind, While, For, StructLit, Statement, StatementKind, Type, TypeConstr, Uop, Value, VariantShape};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
 
+/// compiles a program the type checker accepted to wat
 pub fn compile(ast: Vec<Ast>) -> Result<String, CompilerError> {
     let mut compiler = Compiler::new(ast);
     let comped = compiler.compile()?;
-    return fill_in(comped)
+    fill_in(comped)
 }
 
 fn fill_in(input: String) -> Result<String, CompilerError> {
@@ -31,69 +34,156 @@ fn fill_in(input: String) -> Result<String, CompilerError> {
             }
         })
         .collect::<Vec<_>>();
-    return Ok(a.join(""));
+    Ok(a.join(""))
 }
 
 struct Compiler {
     ast: Vec<Ast>,
-    indent: i32,
+    /// runtime support functions the program needs by name, added to the end of the module
+    helpers: BTreeMap<String, String>,
+    /// locals declared in the function being compiled, not including its params
+    locals: Vec<(String, Type)>,
+    /// the wasm local holding each variable in scope, innermost scope last
+    scopes: Vec<HashMap<String, String>>,
+    /// the loops around the code being compiled, innermost last.
+    /// holds the label of the loop and the number of its wasm labels
+    loops: Vec<(Option<String>, usize)>,
+    /// how many loops have been compiled, used to give each one unique wasm labels
+    loop_count: usize,
+    /// the memory layout of every struct in the program
+    structs: HashMap<String, Layout>,
+    /// the memory layout of every enum in the program
+    enums: HashMap<String, EnumLayout>,
+    /// whether an imported wat file declared the module's memory
+    has_memory: bool,
 }
 
+/// where the fields of a struct are in memory. structs live on the heap and
+/// are passed around as a pointer, fields holding a struct hold a pointer too.
+/// binding a struct to a variable, field or param copies it, see `compile_bound`
+#[derive(Clone)]
+struct Layout {
+    /// name, type and offset of each field, in declaration order
+    fields: Vec<(String, Type, u32)>,
+    /// padded to the alignment of the largest field
+    size: u32,
+}
+
+impl Layout {
+    /// lays the fields out in order from `start`, each aligned to its own size
+    fn new(args: &[Arg], start: u32) -> Layout {
+        let mut fields = Vec::new();
+        let mut offset: u32 = start;
+        let mut align = 1;
+        for field in args {
+            let size = field.tp.bits() / 8;
+            offset = offset.next_multiple_of(size);
+            fields.push((field.name.clone(), field.tp.clone(), offset));
+            offset += size;
+            align = align.max(size);
+        }
+        Layout {
+            fields,
+            size: offset.next_multiple_of(align),
+        }
+    }
+}
 
-#[derive(Debug)]
-pub struct CompilerError {
-    msg: String,
-    line: u32,
+/// enums are tagged unions: a value starts with the index of its variant as an
+/// i32, followed by the fields of that variant
+#[derive(Clone)]
+struct EnumLayout {
+    /// name, shape and field layout of each variant, in declaration order
+    variants: Vec<(String, VariantShape, Layout)>,
 }
 
-impl Display for CompilerError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} on line {}", self.msg, self.line)
+impl EnumLayout {
+    fn new(e: &Enum) -> EnumLayout {
+        let variants = e.variants.iter()
+            .map(|v| (v.name.clone(), v.shape, Layout::new(&v.fields, 4)))
+            .collect();
+        EnumLayout { variants }
+    }
+    /// the tag of a variant
+    fn tag(&self, variant: &str) -> usize {
+        self.variants.iter().position(|(v, _, _)| v == variant).expect("the type checker only allows existing variants")
     }
 }
 
-impl Error for CompilerError {}
+pub type CompilerError = Diagnostic;
 
 impl Compiler {
     fn new(ast: Vec<Ast>) -> Compiler {
-        Compiler { 
-            ast, 
-            indent: 1,
+        let mut structs = HashMap::new();
+        let mut enums = HashMap::new();
+        for section in &ast {
+            let Ast::Statement(s) = section else {
+                continue;
+            };
+            match &s.kind {
+                StatementKind::Struct(s) => {
+                    structs.insert(s.name.clone(), Layout::new(&s.fields, 0));
+                }
+                StatementKind::Enum(e) => {
+                    enums.insert(e.name.clone(), EnumLayout::new(e));
+                }
+                _ => {}
+            }
+        }
+        Compiler {
+            ast,
+            helpers: BTreeMap::new(),
+            locals: Vec::new(),
+            scopes: Vec::new(),
+            loops: Vec::new(),
+            loop_count: 0,
+            structs,
+            enums,
+            has_memory: false,
         }
     }
     fn compile(&mut self) -> Result<String, CompilerError> {
         let mut out = String::from("(module\n");
         for section in self.ast.clone() {
             let a = match section {
-                Ast::Expression(_) => todo!(),
-                Ast::Statement(statmt) => match statmt {
-                    crate::ast::Statement::Function(func) => self.compile_fn_def(func),
-                    crate::ast::Statement::Struct(_) => continue,
-                    crate::ast::Statement::Enum(_) => todo!(),
-                    crate::ast::Statement::Let(_) => todo!(),
-                    crate::ast::Statement::Import(import) => self.compile_import(import),
+                Ast::Expression(_) => Err(Diagnostic::error("expressions are not allowed at the top level", section.span())
+                    .with_help("move it into a function")),
+                Ast::Statement(statmt) => match statmt.kind {
+                    StatementKind::Function(func) => self.compile_fn_def(func),
+                    StatementKind::Struct(_) | StatementKind::Enum(_) => continue,
+                    StatementKind::Break(_) | StatementKind::Continue(_) => Err(Diagnostic::error("`break` and `continue` are only allowed inside a loop", statmt.span)),
+                    StatementKind::Return(_) => Err(Diagnostic::error("`return` is only allowed inside a function", statmt.span)),
+                    StatementKind::Expression(_) => Err(Diagnostic::error("expressions are not allowed at the top level", statmt.span)
+                        .with_help("move it into a function")),
+                    StatementKind::Let(_) | StatementKind::Assign(_) => Err(Diagnostic::error("global variables are not supported", statmt.span)
+                        .with_help("move it into a function")),
+                    StatementKind::Import(import) => self.compile_import(import, statmt.span),
                 },
             };
-            out.push_str(&a.unwrap());
-            // println!("compiled:\n{}", a.unwrap());
+            out.push_str(&a?);
         }
-        out.push_str(")");
-        return Ok(out)
+        for helper in self.helpers.values() {
+            out.push_str(helper);
+        }
+        // the heap needs a memory, which programs that import nothing don't have
+        if self.helpers.contains_key("__alloc") && !self.has_memory {
+            out.push_str("(memory (export \"memory\") 1)\n");
+        }
+        out.push(')');
+        Ok(out)
     }
-    fn compile_import(&mut self, import: Import) -> Result<String, CompilerError> {
+    fn compile_import(&mut self, import: Import, span: Span) -> Result<String, CompilerError> {
         let content = match fs::read_to_string(import.path) {
             Ok(c) => c,
-            Err(e) => { 
-                return Err(CompilerError {
-                    msg: e.to_string(),
-                    line: 0,
-                });
+            Err(e) => {
+                return Err(Diagnostic::error("could not read import", span).with_span_label(e));
             }
         };
         let content = content.trim();
         let content = content.trim_start_matches("(module");
         let mut content = content.trim_end_matches(')').trim().to_string();
-        content.push_str("\n");
+        content.push('\n');
+        self.has_memory |= content.lines().any(|l| l.trim_start().starts_with("(memory"));
 
         Ok(content)
     }
@@ -103,137 +193,1207 @@ impl Compiler {
         if &func.name == "main" {
             out.push_str(" (export \"_start\")");
         }
+        self.locals.clear();
+        let mut params = HashMap::new();
         for arg in func.args {
-            out.push_str(" (param $");
-            out.push_str(&arg.name);
-            // for now we asume the type is stored in memeory and so we are passing a ptr
-            out.push_str(" i32");
-            out.push_str(")");
+            // anything that isn't a number is stored in memory and passed as a ptr
+            out.push_str(&format!(" (param ${} {})", arg.name, arg.tp.wasm_type()));
+            params.insert(arg.name.clone(), arg.name);
         }
         if &func.ret_tp.name != "()" {
-            out.push_str(" (result i32)");
+            out.push_str(&format!(" (result {})", func.ret_tp.wasm_type()));
         }
-        out.push_str("\n");
+        out.push('\n');
 
-        let body = self.compile_block(func.body)?;
+        self.scopes = vec![params];
+        let returns_value = func.ret_tp.name != "()";
+        let body = self.compile_block(func.body, returns_value.then_some(&func.ret_tp))?;
+        for (name, tp) in &self.locals {
+            out.push_str(&format!("(local ${} {})\n", name, tp.wasm_type()));
+        }
         out.push_str(&body);
 
         out.push_str(")\n");
         Ok(out)
     }
-    fn compile_block(&mut self, block: Vec<Ast>) -> Result<String, CompilerError> {
+    /// compiles the sections of a block. values of expressions are dropped, except
+    /// for the last one when the block produces a `value` of the given type
+    fn compile_block(&mut self, block: Vec<Ast>, value: Option<&Type>) -> Result<String, CompilerError> {
+        self.scopes.push(HashMap::new());
         let mut out = String::new();
-        for ast in block {
+        let len = block.len();
+        let mut has_value = false;
+        for (i, ast) in block.into_iter().enumerate() {
             let res = match ast {
-                Ast::Expression(expr) => self.compile_expr(expr)?.join("\n"),
-                Ast::Statement(_) => todo!(),
+                Ast::Expression(expr) if value.is_some() && i + 1 == len => {
+                    has_value = expr.tp().name != "()";
+                    self.compile_expr(expr)?.join("\n")
+                }
+                Ast::Expression(expr) | Ast::Statement(Statement { kind: StatementKind::Expression(expr), .. }) => {
+                    let drop = expr.tp().name != "()";
+                    let mut res = self.compile_expr(expr)?;
+                    // the value of an expression statement isn't used
+                    if drop {
+                        res.push(String::from("(drop)"));
+                    }
+                    res.join("\n")
+                }
+                Ast::Statement(statmt) => self.compile_statement(statmt)?,
             };
             out.push_str(&res);
+            out.push('\n');
+        }
+        // a block that only ends through `return` or `break` leaves nothing on the stack
+        if value.is_some() && !has_value {
+            out.push_str("(unreachable)\n");
+        }
+        self.scopes.pop();
+        Ok(out)
+    }
+    /// compiles a statement inside a function body
+    fn compile_statement(&mut self, statmt: Statement) -> Result<String, CompilerError> {
+        match statmt.kind {
+            StatementKind::Let(l) => self.compile_let(l),
+            StatementKind::Assign(a) => self.compile_assign(a),
+            StatementKind::Expression(_) => unreachable!("expression statements are compiled by `compile_block`"),
+            StatementKind::Return(value) => self.compile_return(value),
+            StatementKind::Break(label) => Ok(format!("(br $brk_{})", self.loop_target("break", label, statmt.span)?)),
+            StatementKind::Continue(label) => Ok(format!("(br $cont_{})", self.loop_target("continue", label, statmt.span)?)),
+            StatementKind::Function(_) | StatementKind::Struct(_) | StatementKind::Enum(_) | StatementKind::Import(_) => {
+                unreachable!("the type checker only allows items at the top level")
+            }
+        }
+    }
+    fn compile_let(&mut self, l: Let) -> Result<String, CompilerError> {
+        // compiled before declaring the local so the value can read a variable it shadows
+        let value = l.value.map(|v| self.compile_bound(v)).transpose()?;
+        let local = self.declare_local(&l.name, l.tp);
+        match value {
+            Some(mut out) => {
+                out.push(format!("(local.set ${})", local));
+                Ok(out.join("\n"))
+            }
+            // `let x;` leaves the local at zero until it is assigned
+            None => Ok(String::new()),
+        }
+    }
+    fn compile_assign(&mut self, a: Assign) -> Result<String, CompilerError> {
+        let tp = a.target.tp().clone();
+        // `x += v` is compiled as `x = x + v`
+        let value = match a.op {
+            Some(op) => {
+                let span = a.target.span.to(a.value.span);
+                Expression {
+                    kind: ExpressionKind::BinaryOperation(Box::new(a.target.clone()), op, Box::new(a.value)),
+                    span,
+                    tp: Some(tp.clone()),
+                }
+            }
+            None => a.value,
+        };
+        match a.target.kind {
+            ExpressionKind::Field(obj, field) => {
+                let offset = self.field_offset(&obj, &field);
+                let mut out = self.compile_expr(*obj)?;
+                out.append(&mut self.compile_bound(value)?);
+                out.push(format!("({} offset={})", store_instr(&tp), offset));
+                Ok(out.join("\n"))
+            }
+            ExpressionKind::Value(Value::Var(name)) => {
+                let local = self.lookup(&name).to_string();
+                let mut out = self.compile_bound(value)?;
+                out.push(format!("(local.set ${})", local));
+                Ok(out.join("\n"))
+            }
+            _ => unreachable!("the parser only assigns to variables and their fields"),
         }
+    }
+    /// the offset of a field of the struct `obj` evaluates to
+    fn field_offset(&self, obj: &Expression, field: &str) -> u32 {
+        let layout = &self.structs[&obj.tp().name];
+        layout.fields.iter().find(|(name, _, _)| name == field).map(|(_, _, offset)| *offset).unwrap()
+    }
+    fn compile_struct_lit(&mut self, lit: StructLit) -> Result<Vec<String>, CompilerError> {
+        let layout = self.structs[&lit.name].clone();
+        self.compile_alloc(Type::new(&lit.name), &layout, None, lit.fields)
+    }
+    fn compile_enum_lit(&mut self, lit: EnumLit) -> Result<Vec<String>, CompilerError> {
+        let layout = &self.enums[&lit.name];
+        let tag = layout.tag(&lit.variant);
+        let fields = layout.variants[tag].2.clone();
+        self.compile_alloc(Type::new(&lit.name), &fields, Some(tag), lit.fields)
+    }
+    /// allocates a struct or enum value on the heap and stores its tag, if it
+    /// has one, and every field, leaving the pointer on the stack
+    fn compile_alloc(&mut self, tp: Type, layout: &Layout, tag: Option<usize>, fields: Vec<(String, Span, Expression)>) -> Result<Vec<String>, CompilerError> {
+        self.helpers.entry(String::from("__alloc")).or_insert(String::from(ALLOC));
+        // the dot keeps it from clashing with a variable
+        let ptr = self.declare_local(".ptr", tp);
+        let mut out = vec![
+            format!("(i32.const {})", layout.size),
+            String::from("(call $__alloc)"),
+            format!("(local.set ${})", ptr),
+        ];
+        if let Some(tag) = tag {
+            out.push(format!("(local.get ${})\n(i32.const {})\n(i32.store offset=0)", ptr, tag));
+        }
+        for (field, _, value) in fields {
+            let (_, tp, offset) = layout.fields.iter().find(|(f, _, _)| f == &field).unwrap();
+            out.push(format!("(local.get ${})", ptr));
+            out.append(&mut self.compile_bound(value)?);
+            out.push(format!("({} offset={})", store_instr(tp), offset));
+        }
+        out.push(format!("(local.get ${})", ptr));
         Ok(out)
     }
+    /// compiles a value that is being bound to a variable, field or param.
+    /// structs have value semantics, so one read from somewhere else is copied
+    /// and changing its fields doesn't change the original. literals and call
+    /// results aren't bound to anything yet, so they are used as they are
+    fn compile_bound(&mut self, value: Expression) -> Result<Vec<String>, CompilerError> {
+        let tp = value.tp().clone();
+        let fresh = matches!(value.kind, ExpressionKind::Value(Value::StructLit(_) | Value::FnCall(_)));
+        let mut out = self.compile_expr(value)?;
+        if !fresh && self.is_struct(&tp) {
+            out.push(format!("(call ${})", self.copy_helper(&tp.name)));
+        }
+        Ok(out)
+    }
+    fn is_struct(&self, tp: &Type) -> bool {
+        // strings are never changed in place, even when the program declares a struct named `String`
+        self.structs.contains_key(&tp.name) && tp.name != "String"
+    }
+    /// adds the helper copying a struct to the module and returns its name.
+    /// the structs in its fields are copied too
+    fn copy_helper(&mut self, name: &str) -> String {
+        let helper = format!("__copy_{}", name);
+        if self.helpers.contains_key(&helper) {
+            return helper;
+        }
+        self.helpers.entry(String::from("__alloc")).or_insert(String::from(ALLOC));
+        // added before its body is known, so a struct holding itself doesn't recurse forever
+        self.helpers.insert(helper.clone(), String::new());
+        let layout = self.structs[name].clone();
+        let mut func = format!("(func ${} (param $ptr i32) (result i32)
+    (local $new i32)
+    (local.set $new (call $__alloc (i32.const {1})))
+    (memory.copy (local.get $new) (local.get $ptr) (i32.const {1}))
+", helper, layout.size);
+        for (_, tp, offset) in &layout.fields {
+            if self.is_struct(tp) {
+                let copy = self.copy_helper(&tp.name);
+                func.push_str(&format!(
+                    "    (i32.store offset={0} (local.get $new) (call ${1} (i32.load offset={0} (local.get $new))))\n",
+                    offset, copy,
+                ));
+            }
+        }
+        func.push_str("    (local.get $new))\n");
+        self.helpers.insert(helper.clone(), func);
+        helper
+    }
+    /// adds a wasm local for the variable `name` to the current function and scope.
+    /// shadowed variables get their own local with a number after the name
+    fn declare_local(&mut self, name: &str, tp: Type) -> String {
+        let taken = |n: &String| {
+            self.locals.iter().any(|(l, _)| l == n) || self.scopes.first().is_some_and(|s| s.values().any(|v| v == n))
+        };
+        let mut local = name.to_string();
+        let mut i = 1;
+        while taken(&local) {
+            local = format!("{}_{}", name, i);
+            i += 1;
+        }
+        self.locals.push((local.clone(), tp));
+        self.scopes.last_mut().unwrap().insert(name.to_string(), local.clone());
+        local
+    }
+    /// the wasm local holding a variable
+    fn lookup(&self, name: &str) -> &str {
+        self.scopes.iter().rev().find_map(|s| s.get(name)).expect("the parser only produces variables that are in scope")
+    }
     fn compile_expr(&mut self, expr: Expression) -> Result<Vec<String>, CompilerError> {
-        match expr {
-            Expression::Value(v) => self.compile_val(v),
-            Expression::BinaryOperation(lhs, op, rhs) => self.compile_binary_op(*lhs, op, *rhs),
-            Expression::UnaryOperation(_, _) => todo!(),
-            Expression::If(i) => self.compile_if(i),
+        let tp = expr.tp().clone();
+        match expr.kind {
+            ExpressionKind::Value(Value::StructLit(lit)) => self.compile_struct_lit(lit),
+            ExpressionKind::Value(Value::EnumLit(lit)) => self.compile_enum_lit(lit),
+            ExpressionKind::Value(v) => self.compile_val(v, &tp),
+            ExpressionKind::Field(obj, field) => {
+                let offset = self.field_offset(&obj, &field);
+                let mut out = self.compile_expr(*obj)?;
+                out.push(format!("({} offset={})", load_instr(&tp), offset));
+                Ok(out)
+            }
+            ExpressionKind::BinaryOperation(lhs, op, rhs) => self.compile_binary_op(*lhs, op, *rhs),
+            ExpressionKind::UnaryOperation(op, expr) => self.compile_unary_op(op, *expr),
+            ExpressionKind::If(i) => self.compile_if(i, &tp),
+            ExpressionKind::Match(m) => self.compile_match(m, &tp),
+            ExpressionKind::While(w) => self.compile_while(w),
+            ExpressionKind::For(l) => self.compile_for(l),
+            ExpressionKind::Range(..) => unreachable!("the type checker only allows ranges in `for` loops"),
+            ExpressionKind::Cast(value, to) => {
+                let from = value.tp().clone();
+                let mut out = self.compile_expr(*value)?;
+                out.append(&mut convert(&from, &to));
+                Ok(out)
+            }
+            ExpressionKind::Index(value, index) => {
+                self.helpers.entry(String::from("__char_at")).or_insert(String::from(CHAR_AT));
+                let mut out = self.compile_expr(*value)?;
+                out.append(&mut self.compile_expr(*index)?);
+                out.push(String::from("(call $__char_at)"));
+                Ok(out)
+            }
+            ExpressionKind::Block(block) => {
+                let value = (tp.name != "()").then_some(&tp);
+                let mut out = match value {
+                    Some(tp) => vec![format!("(block (result {})", tp.wasm_type())],
+                    None => vec![String::from("(block")],
+                };
+                out.push(self.compile_block(block, value)?);
+                out.push(String::from(")"));
+                Ok(out)
+            }
         }
     }
+    fn compile_unary_op(&mut self, op: Uop, expr: Expression) -> Result<Vec<String>, CompilerError> {
+        let tp = expr.tp().clone();
+        let w = tp.wasm_type();
+        let mut out = Vec::new();
+        if tp.is_float() {
+            out.append(&mut self.compile_expr(expr)?);
+            out.push(format!("({}.neg)", w));
+            return Ok(out);
+        }
+        match op {
+            Uop::Neg => {
+                // wasm has no integer negation, subtract from zero instead
+                out.push(format!("({}.const 0)", w));
+                out.append(&mut self.compile_expr(expr)?);
+                out.push(format!("({}.sub)", w));
+                out.append(&mut wrap(&tp));
+            }
+            Uop::Not => {
+                out.append(&mut self.compile_expr(expr)?);
+                out.push(format!("({}.eqz)", w));
+            }
+            Uop::BitNot => {
+                out.append(&mut self.compile_expr(expr)?);
+                out.push(format!("({}.const -1)", w));
+                out.push(format!("({}.xor)", w));
+                out.append(&mut wrap(&tp));
+            }
+        }
+        Ok(out)
+    }
     fn compile_binary_op(&mut self, lhs: Expression, op: Bop, rhs: Expression) -> Result<Vec<String>, CompilerError> {
+        // the type of the operands, comparisons themselves are always bool
+        let tp = lhs.tp().clone();
+
         let mut out = Vec::new();
         out.append(&mut self.compile_expr(lhs)?);
+        if tp.name == "String" {
+            // the type checker only allows pushing a char
+            self.helpers.entry(String::from("__alloc")).or_insert(String::from(ALLOC));
+            self.helpers.entry(String::from("__push_char")).or_insert(String::from(PUSH_CHAR));
+            out.append(&mut self.compile_expr(rhs)?);
+            out.push(String::from("(call $__push_char)"));
+            return Ok(out);
+        }
+        if op.is_logical() {
+            // the rhs is only evaluated when the lhs doesn't already decide the result
+            let rhs = self.compile_expr(rhs)?.join("\n");
+            let (then, els) = match op {
+                Bop::And => (rhs, String::from("(i32.const 0)")),
+                _ => (String::from("(i32.const 1)"), rhs),
+            };
+            out.push(format!("(if (result i32)\n(then\n{}\n)\n(else\n{}\n)\n)", then, els));
+            return Ok(out);
+        }
         out.append(&mut self.compile_expr(rhs)?);
-        out.push(match op {
-            Bop::Plus => todo!(),
-            Bop::Minus => todo!(),
-            Bop::Slash => todo!(),
-            Bop::Star => todo!(),
-            Bop::Eq => String::from("(i32.eq)"),
-            Bop::Gt => String::from("(i32.gt_u)"),
-            Bop::Lt => todo!(),
-            Bop::GtEq => todo!(),
-            Bop::LtEq => todo!(),
-            Bop::Carret => todo!(),
-        });
+
+        let w = tp.wasm_type();
+        if tp.is_float() {
+            let instr = match op {
+                Bop::Plus => "add",
+                Bop::Minus => "sub",
+                Bop::Slash => "div",
+                Bop::Star => "mul",
+                Bop::Eq => "eq",
+                Bop::NotEq => "ne",
+                Bop::Gt => "gt",
+                Bop::Lt => "lt",
+                Bop::GtEq => "ge",
+                Bop::LtEq => "le",
+                Bop::Rem | Bop::Shl | Bop::Shr | Bop::Carret | Bop::And | Bop::Or => {
+                    unreachable!("the type checker only allows `{}` on integers", op)
+                }
+            };
+            out.push(format!("({}.{})", w, instr));
+            return Ok(out);
+        }
+
+        let sign = if tp.is_signed() { "s" } else { "u" };
+        let instr = match op {
+            Bop::Plus => String::from("add"),
+            Bop::Minus => String::from("sub"),
+            Bop::Star => String::from("mul"),
+            // dividing by zero at runtime traps with "integer divide by zero"
+            Bop::Slash => format!("div_{}", sign),
+            Bop::Rem => format!("rem_{}", sign),
+            Bop::Shl => String::from("shl"),
+            Bop::Shr => format!("shr_{}", sign),
+            Bop::Eq => String::from("eq"),
+            Bop::NotEq => String::from("ne"),
+            Bop::Gt => format!("gt_{}", sign),
+            Bop::Lt => format!("lt_{}", sign),
+            Bop::GtEq => format!("ge_{}", sign),
+            Bop::LtEq => format!("le_{}", sign),
+            Bop::Carret => {
+                let (name, code) = pow_helper(&tp);
+                self.helpers.entry(name.clone()).or_insert(code);
+                out.push(format!("(call ${})", name));
+                out.append(&mut wrap(&tp));
+                return Ok(out);
+            }
+            Bop::And | Bop::Or => unreachable!("`&&` and `||` are compiled above"),
+        };
+        out.push(format!("({}.{})", w, instr));
+        if !op.is_comparison() {
+            out.append(&mut wrap(&tp));
+        }
         Ok(out)
     }
-    fn compile_if(&mut self, i: If) -> Result<Vec<String>, CompilerError> {
+    /// `else if` chains become an `if` nested in the `else` of the one before
+    fn compile_if(&mut self, i: If, tp: &Type) -> Result<Vec<String>, CompilerError> {
+        let value = (tp.name != "()").then_some(tp);
         let mut out = Vec::new();
         out.append(&mut self.compile_expr(*i.condition)?);
-        out.push(String::from("(if\n"));
+        match value {
+            Some(tp) => out.push(format!("(if (result {})\n", tp.wasm_type())),
+            None => out.push(String::from("(if\n")),
+        }
         out.push(String::from("(then\n"));
-        out.push(self.compile_block(i.block)?);
+        out.push(self.compile_block(i.block, value)?);
         out.push(String::from(")\n"));
-        if i.els.is_some() {
+        let mut elsifs = i.elsifs.unwrap_or_default().into_iter();
+        if let Some(elsif) = elsifs.next() {
+            let rest = elsifs.collect::<Vec<_>>();
+            let nested = If {
+                condition: elsif.condition,
+                block: elsif.block,
+                elsifs: if rest.is_empty() { None } else { Some(rest) },
+                els: i.els,
+            };
+            out.push(String::from("(else\n"));
+            out.append(&mut self.compile_if(nested, tp)?);
+            out.push(String::from(")\n"));
+        } else if let Some(els) = i.els {
             out.push(String::from("(else\n"));
-            out.push(self.compile_block(i.els.unwrap())?);
+            out.push(self.compile_block(els, value)?);
             out.push(String::from(")\n"));
         }
         out.push(String::from(")\n"));
 
         Ok(out)
     }
-    fn compile_val(&mut self, value: Value) -> Result<Vec<String>, CompilerError> {
+    /// the variables a pattern binds, with their type and where in the matched
+    /// value they are loaded from. `None` binds the value itself
+    fn bindings(&self, pattern: &Pattern, tp: &Type) -> Vec<(String, Type, Option<u32>)> {
+        match &pattern.kind {
+            PatternKind::Wildcard => Vec::new(),
+            PatternKind::Binding(name) => vec![(name.clone(), tp.clone(), None)],
+            PatternKind::Variant { variant, fields, .. } => {
+                let layout = &self.enums[&tp.name];
+                let fields_layout = &layout.variants[layout.tag(variant)].2.fields;
+                fields.iter().filter_map(|(field, sub)| {
+                    let PatternKind::Binding(name) = &sub.kind else {
+                        return None;
+                    };
+                    let (_, field_tp, offset) = fields_layout.iter().find(|(f, _, _)| f == field).unwrap();
+                    Some((name.clone(), field_tp.clone(), Some(*offset)))
+                }).collect()
+            }
+        }
+    }
+    /// keeps the value in a local and compares its tag with each arm in turn.
+    /// like `else if`, every arm is nested in the `else` of the one before
+    fn compile_match(&mut self, m: Match, tp: &Type) -> Result<Vec<String>, CompilerError> {
+        let value_tp = m.value.tp().clone();
+        let mut out = self.compile_expr(*m.value)?;
+        let local = self.declare_local(".match", value_tp.clone());
+        out.push(format!("(local.set ${})", local));
+        out.append(&mut self.compile_arms(m.arms, &local, &value_tp, tp)?);
+        Ok(out)
+    }
+    fn compile_arms(&mut self, mut arms: Vec<MatchArm>, local: &str, value_tp: &Type, tp: &Type) -> Result<Vec<String>, CompilerError> {
+        let value = (tp.name != "()").then_some(tp);
+        if arms.is_empty() {
+            return Ok(vec![String::from("(unreachable)")]);
+        }
+        let arm = arms.remove(0);
+
+        self.scopes.push(HashMap::new());
+        let mut body = Vec::new();
+        for (name, field_tp, offset) in self.bindings(&arm.pattern, value_tp) {
+            let var = self.declare_local(&name, field_tp.clone());
+            body.push(format!("(local.get ${})", local));
+            if let Some(offset) = offset {
+                body.push(format!("({} offset={})", load_instr(&field_tp), offset));
+            }
+            body.push(format!("(local.set ${})", var));
+        }
+        let block = self.compile_block(arm.body, value);
+        self.scopes.pop();
+        body.push(block?);
+
+        // arms after one that matches anything are never reached
+        let PatternKind::Variant { variant, .. } = &arm.pattern.kind else {
+            return Ok(body);
+        };
+        let tag = self.enums[&value_tp.name].tag(variant);
+        let mut out = vec![
+            format!("(local.get ${})\n(i32.load offset=0)\n(i32.const {})\n(i32.eq)", local, tag),
+            match value {
+                Some(tp) => format!("(if (result {})", tp.wasm_type()),
+                None => String::from("(if"),
+            },
+            String::from("(then"),
+        ];
+        out.append(&mut body);
+        out.push(String::from(")\n(else"));
+        out.append(&mut self.compile_arms(arms, local, value_tp, tp)?);
+        out.push(String::from(")\n)"));
+        Ok(out)
+    }
+    /// `block` is the target of `break`, `loop` the target of `continue`
+    fn compile_while(&mut self, w: While) -> Result<Vec<String>, CompilerError> {
+        let n = self.loop_count;
+        self.loop_count += 1;
+        let mut out = vec![format!("(block $brk_{}", n), format!("(loop $cont_{}", n)];
+        out.append(&mut self.compile_expr(*w.condition)?);
+        out.push(String::from("(i32.eqz)"));
+        out.push(format!("(br_if $brk_{})", n));
+
+        self.loops.push((w.label, n));
+        let body = self.compile_block(w.block, None);
+        self.loops.pop();
+        out.push(body?);
+
+        out.push(format!("(br $cont_{})", n));
+        out.push(String::from(")\n)"));
+        Ok(out)
+    }
+    fn compile_return(&mut self, value: Option<Expression>) -> Result<String, CompilerError> {
+        let mut out = match value {
+            Some(value) => self.compile_expr(value)?,
+            None => Vec::new(),
+        };
+        out.push(String::from("(return)"));
+        Ok(out.join("\n"))
+    }
+    /// counts the loop variable from the start to the end of a range. the body
+    /// is wrapped in the `continue` block so `continue` still increments it
+    fn compile_for(&mut self, l: For) -> Result<Vec<String>, CompilerError> {
+        let ExpressionKind::Range(start, end, inclusive) = l.iter.kind else {
+            unreachable!("the type checker only allows `for` loops over ranges")
+        };
+        let tp = start.tp().clone();
+        let n = self.loop_count;
+        self.loop_count += 1;
+        let w = tp.wasm_type();
+        let sign = if tp.is_signed() { "s" } else { "u" };
+
+        let mut out = self.compile_expr(*start)?;
+        let mut end = self.compile_expr(*end)?;
+        self.scopes.push(HashMap::new());
+        let var = self.declare_local(&l.var, tp.clone());
+        let end_local = self.declare_local(".end", tp);
+        out.push(format!("(local.set ${})", var));
+        out.append(&mut end);
+        out.push(format!("(local.set ${})", end_local));
+
+        out.push(format!("(block $brk_{}", n));
+        out.push(format!("(loop $top_{}", n));
+        out.push(format!("(local.get ${})\n(local.get ${})", var, end_local));
+        let cmp = if inclusive { "gt" } else { "ge" };
+        out.push(format!("({}.{}_{})\n(br_if $brk_{})", w, cmp, sign, n));
+
+        self.loops.push((l.label, n));
+        let body = self.compile_block(l.block, None);
+        self.loops.pop();
+        self.scopes.pop();
+        out.push(format!("(block $cont_{}\n{})", n, body?));
+
+        // stop before incrementing past the end, `0..=255` would never end for a u8
+        if inclusive {
+            out.push(format!("(local.get ${})\n(local.get ${})\n({}.eq)\n(br_if $brk_{})", var, end_local, w, n));
+        }
+        out.push(format!("(local.get ${})\n({}.const 1)\n({}.add)\n(local.set ${})", var, w, w, var));
+        out.push(format!("(br $top_{})", n));
+        out.push(String::from(")\n)"));
+        Ok(out)
+    }
+    /// the number of the loop a `break` or `continue` jumps out of
+    fn loop_target(&self, keyword: &str, label: Option<String>, span: Span) -> Result<usize, CompilerError> {
+        match label {
+            Some(label) => match self.loops.iter().rev().find(|(l, _)| l.as_ref() == Some(&label)) {
+                Some((_, n)) => Ok(*n),
+                None => Err(Diagnostic::error(format!("use of undeclared label `'{}`", label), span)
+                    .with_span_label("no loop with this label around it")),
+            },
+            None => match self.loops.last() {
+                Some((_, n)) => Ok(*n),
+                None => Err(Diagnostic::error(format!("`{}` outside of a loop", keyword), span)
+                    .with_span_label(format!("cannot `{}` outside of a loop", keyword))),
+            },
+        }
+    }
+    fn compile_val(&mut self, value: Value, tp: &Type) -> Result<Vec<String>, CompilerError> {
         let mut ret = Vec::new();
         match value {
-            Value::F32(_) => todo!(),
-            Value::I32(i32) => {
-                ret.push(format!("(i32.const {})", i32));
+            Value::Bool(b) => {
+                ret.push(format!("(i32.const {})", b as i32));
                 Ok(ret)
             }
-            Value::TypeConstr(tp) => {
-                ret.push(self.compile_type_construction(tp.clone())?);
-                ret.push(format!("(i32.const ~type-{}~)", tp.name));
+            Value::Char(c) => {
+                ret.push(format!("(i32.const {})", c as u32));
                 Ok(ret)
-            },
-            Value::Var(_) => todo!(),
+            }
+            Value::Float(f, _) => {
+                ret.push(format!("({}.const {})", tp.wasm_type(), f));
+                Ok(ret)
+            }
+            Value::Int(i, _) => {
+                ret.push(format!("({}.const {})", tp.wasm_type(), i));
+                Ok(ret)
+            }
+            Value::TypeConstr(tp) => self.compile_type_construction(tp),
+            Value::Var(name) => {
+                ret.push(format!("(local.get ${})", self.lookup(&name)));
+                Ok(ret)
+            }
             Value::FnCall(fncall) => {
-                let mut out = Vec::new(); //vec![format!("(call ${}", fncall.name)];
-
-                let mut args = String::new();
+                let mut out = Vec::new();
+                // leave every argument on the stack in order, then call
                 for arg in fncall.args {
-
-                    let mut res = self.compile_expr(arg)?;
-                    args.push_str(&res.pop().unwrap());
-                    for res in res {
-                        out.push(res);
-                    }
+                    out.append(&mut self.compile_bound(arg)?);
                 }
-
-                out.push(format!("(call ${} {})", fncall.name, args));
+                out.push(format!("(call ${})", fncall.name));
 
                 Ok(out)
             },
+            Value::StructLit(_) | Value::EnumLit(_) => unreachable!("struct and enum literals are compiled by `compile_expr`"),
         }
     }
-    fn compile_type_construction(&mut self, tp: TypeConstr) -> Result<String, CompilerError> {
+    /// a string is allocated on the heap as its length in bytes, followed by
+    /// its utf-8 bytes. the pointer to it is left on the stack
+    fn compile_type_construction(&mut self, tp: TypeConstr) -> Result<Vec<String>, CompilerError> {
         // todo: make this something that can be done to arbatrary types vea a trait
         if tp.name == "String" {
-            let mut out = String::new();
+            self.helpers.entry(String::from("__alloc")).or_insert(String::from(ALLOC));
+            let ptr = self.declare_local(".ptr", Type::new("String"));
+            let mut out = Vec::new();
             for v in &tp.values {
                 match v.1 {
                     Constant::Value(_) => todo!(),
                     Constant::Arr(arr) => {
-                        out.push_str(&format!("(i32.store (i32.const ~type-String~) (i32.const {}))\n", arr.len()));
-                        for (idx, char) in arr.iter().enumerate() {
-                            out.push_str(&format!("(i32.store (i32.const ~type-String+{}~) (i32.const {}))\n", idx + 4, char.get_number().unwrap()));
+                        out.push(format!("(i32.const {})\n(call $__alloc)\n(local.set ${})", arr.len() + 4, ptr));
+                        out.push(format!("(i32.store (local.get ${}) (i32.const {}))", ptr, arr.len()));
+                        for (idx, byte) in arr.iter().enumerate() {
+                            out.push(format!("(i32.store8 offset={} (local.get ${}) (i32.const {}))", idx + 4, ptr, byte.get_number().unwrap()));
                         }
-                        println!("v: {:?}", arr);
                     },
                 }
             }
+            out.push(format!("(local.get ${})", ptr));
             return Ok(out);
         }
         todo!()
     }
 }
 
+/// 8 and 16 bit integers live in an i32, so after arithmetic the result has
+/// to be wrapped back into range
+fn wrap(tp: &Type) -> Vec<String> {
+    match (tp.bits(), tp.is_signed()) {
+        (8, true) => vec![String::from("(i32.extend8_s)")],
+        (16, true) => vec![String::from("(i32.extend16_s)")],
+        (8, false) => vec![String::from("(i32.const 255)"), String::from("(i32.and)")],
+        (16, false) => vec![String::from("(i32.const 65535)"), String::from("(i32.and)")],
+        _ => Vec::new(),
+    }
+}
+
+/// converts a number on the stack from `from` to `to` the way rust's `as`
+/// does. floats saturate when cast to integers, and NaN becomes 0
+fn convert(from: &Type, to: &Type) -> Vec<String> {
+    let (f, t) = (from.wasm_type(), to.wasm_type());
+    match (from.is_float(), to.is_float()) {
+        (true, true) => match (f, t) {
+            ("f32", "f64") => vec![String::from("(f64.promote_f32)")],
+            ("f64", "f32") => vec![String::from("(f32.demote_f64)")],
+            _ => Vec::new(),
+        },
+        (false, true) => {
+            let sign = if from.is_signed() { "s" } else { "u" };
+            vec![format!("({}.convert_{}_{})", t, f, sign)]
+        }
+        (true, false) => {
+            let sign = if to.is_signed() { "s" } else { "u" };
+            let mut out = Vec::new();
+            // trunc_sat only saturates to 32 or 64 bits, clamp to the range of smaller types first
+            if to.bits() < 32 {
+                let (min, max) = to.int_range();
+                out.push(format!("({0}.const {1})\n({0}.max)\n({0}.const {2})\n({0}.min)", f, min, max));
+            }
+            out.push(format!("({}.trunc_sat_{}_{})", t, f, sign));
+            out
+        }
+        (false, false) => {
+            // 8 and 16 bit integers are already sign or zero extended in their i32
+            let sign = if from.is_signed() { "s" } else { "u" };
+            let mut out = match (f, t) {
+                ("i32", "i64") => vec![format!("(i64.extend_i32_{})", sign)],
+                ("i64", "i32") => vec![String::from("(i32.wrap_i64)")],
+                _ => Vec::new(),
+            };
+            out.append(&mut wrap(to));
+            out
+        }
+    }
+}
+
+/// bump allocator for the heap, which starts after the static data.
+/// allocations are 8 byte aligned and never freed
+const ALLOC: &str = "(global $__heap (mut i32) (i32.const 1024))
+(func $__alloc (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (i32.and (i32.add (global.get $__heap) (i32.const 7)) (i32.const -8)))
+    (global.set $__heap (i32.add (local.get $ptr) (local.get $size)))
+    (if (i32.gt_u (global.get $__heap) (i32.shl (memory.size) (i32.const 16)))
+        (then
+            (if (i32.eq (memory.grow (i32.add (i32.shr_u (local.get $size) (i32.const 16)) (i32.const 1))) (i32.const -1))
+                (then (unreachable)))))
+    (local.get $ptr))
+";
+
+/// `string[index]`, the char that starts after skipping `index` chars of the
+/// utf-8 bytes. traps when the index is negative or past the end
+const CHAR_AT: &str = "(func $__char_at (param $str i32) (param $index i32) (result i32)
+    (local $ptr i32)
+    (local $end i32)
+    (local $char i32)
+    (local $extra i32)
+    (local.set $ptr (i32.add (local.get $str) (i32.const 4)))
+    (local.set $end (i32.add (local.get $ptr) (i32.load (local.get $str))))
+    (if (i32.lt_s (local.get $index) (i32.const 0)) (then (unreachable)))
+    (loop $skip
+        (if (i32.ge_u (local.get $ptr) (local.get $end)) (then (unreachable)))
+        (local.set $char (i32.load8_u (local.get $ptr)))
+        ;; the first byte of a char says how many continuation bytes follow it
+        (local.set $extra
+            (select (i32.const 0)
+                (select (i32.const 1)
+                    (select (i32.const 2) (i32.const 3) (i32.lt_u (local.get $char) (i32.const 0xF0)))
+                    (i32.lt_u (local.get $char) (i32.const 0xE0)))
+                (i32.lt_u (local.get $char) (i32.const 0x80))))
+        (if (i32.ne (local.get $index) (i32.const 0))
+            (then
+                (local.set $ptr (i32.add (local.get $ptr) (i32.add (local.get $extra) (i32.const 1))))
+                (local.set $index (i32.sub (local.get $index) (i32.const 1)))
+                (br $skip))))
+    (local.set $char
+        (i32.and (local.get $char)
+            (select (i32.const 0x7F) (i32.shr_u (i32.const 0x3F) (local.get $extra)) (i32.eqz (local.get $extra)))))
+    (block $done
+        (loop $continuation
+            (br_if $done (i32.eqz (local.get $extra)))
+            (local.set $ptr (i32.add (local.get $ptr) (i32.const 1)))
+            (local.set $char
+                (i32.or (i32.shl (local.get $char) (i32.const 6))
+                    (i32.and (i32.load8_u (local.get $ptr)) (i32.const 0x3F))))
+            (local.set $extra (i32.sub (local.get $extra) (i32.const 1)))
+            (br $continuation)))
+    (local.get $char))
+";
+
+/// `string + c`, a new string with the utf-8 bytes of the char after a copy of
+/// the old one. the old string is left as it was
+const PUSH_CHAR: &str = "(func $__push_char (param $str i32) (param $char i32) (result i32)
+    (local $len i32)
+    (local $bytes i32)
+    (local $new i32)
+    (local $ptr i32)
+    (local $i i32)
+    (local.set $len (i32.load (local.get $str)))
+    (local.set $bytes
+        (select (i32.const 1)
+            (select (i32.const 2)
+                (select (i32.const 3) (i32.const 4) (i32.lt_u (local.get $char) (i32.const 0x10000)))
+                (i32.lt_u (local.get $char) (i32.const 0x800)))
+            (i32.lt_u (local.get $char) (i32.const 0x80))))
+    (local.set $new (call $__alloc (i32.add (i32.add (local.get $len) (local.get $bytes)) (i32.const 4))))
+    (i32.store (local.get $new) (i32.add (local.get $len) (local.get $bytes)))
+    (memory.copy
+        (i32.add (local.get $new) (i32.const 4))
+        (i32.add (local.get $str) (i32.const 4))
+        (local.get $len))
+    (local.set $ptr (i32.add (i32.add (local.get $new) (i32.const 4)) (local.get $len)))
+    (if (i32.eq (local.get $bytes) (i32.const 1))
+        (then (i32.store8 (local.get $ptr) (local.get $char)))
+        (else
+            ;; the first byte is 110xxxxx, 1110xxxx or 11110xxx, the rest are 10xxxxxx
+            (i32.store8 (local.get $ptr)
+                (i32.or
+                    (i32.and (i32.shr_u (i32.const 0xFF00) (local.get $bytes)) (i32.const 0xFF))
+                    (i32.shr_u (local.get $char) (i32.mul (i32.const 6) (i32.sub (local.get $bytes) (i32.const 1))))))
+            (local.set $i (i32.const 1))
+            (loop $continuation
+                (i32.store8 (i32.add (local.get $ptr) (local.get $i))
+                    (i32.or (i32.const 0x80)
+                        (i32.and (i32.const 0x3F)
+                            (i32.shr_u (local.get $char)
+                                (i32.mul (i32.const 6) (i32.sub (i32.sub (local.get $bytes) (i32.const 1)) (local.get $i)))))))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br_if $continuation (i32.lt_u (local.get $i) (local.get $bytes))))))
+    (local.get $new))
+";
+
+/// the instruction reading a value of type `tp` from memory
+fn load_instr(tp: &Type) -> &'static str {
+    match tp.name.as_str() {
+        "i8" => "i32.load8_s",
+        "u8" => "i32.load8_u",
+        "i16" => "i32.load16_s",
+        "u16" => "i32.load16_u",
+        "i64" | "u64" => "i64.load",
+        "f32" => "f32.load",
+        "f64" => "f64.load",
+        _ => "i32.load",
+    }
+}
+fn store_instr(tp: &Type) -> &'static str {
+    match tp.name.as_str() {
+        "i8" | "u8" => "i32.store8",
+        "i16" | "u16" => "i32.store16",
+        "i64" | "u64" => "i64.store",
+        "f32" => "f32.store",
+        "f64" => "f64.store",
+        _ => "i32.store",
+    }
+}
+/// the name and code of the integer power routine for `tp`, `base ^ exp`.
+/// negative exponents truncate towards zero like division does
+fn pow_helper(tp: &Type) -> (String, String) {
+    let w = tp.wasm_type();
+    let name = format!("__pow_{}{}", if tp.is_signed() { "i" } else { "u" }, &w[1..]);
+    let negative = if tp.is_signed() {
+        format!("
+    (if ({w}.lt_s (local.get $exp) ({w}.const 0))
+        (then
+            ;; only 1 and -1 have an integer reciprocal
+            (if ({w}.eq (local.get $base) ({w}.const 1))
+                (then (return ({w}.const 1))))
+            (if ({w}.eq (local.get $base) ({w}.const -1))
+                (then (return (select ({w}.const -1) ({w}.const 1) (i32.eqz ({w}.eqz ({w}.and (local.get $exp) ({w}.const 1))))))))
+            (return ({w}.const 0))))", w = w)
+    } else {
+        String::new()
+    };
+    let code = format!("(func ${name} (param $base {w}) (param $exp {w}) (result {w})
+    (local $acc {w}){negative}
+    (local.set $acc ({w}.const 1))
+    (block $done
+        (loop $next
+            (br_if $done ({w}.eqz (local.get $exp)))
+            (if (i32.eqz ({w}.eqz ({w}.and (local.get $exp) ({w}.const 1))))
+                (then (local.set $acc ({w}.mul (local.get $acc) (local.get $base)))))
+            (local.set $base ({w}.mul (local.get $base) (local.get $base)))
+            (local.set $exp ({w}.shr_u (local.get $exp) ({w}.const 1)))
+            (br $next)))
+    (local.get $acc))
+", name = name, w = w, negative = negative);
+    (name, code)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process::Command};
+
+    use super::compile;
+    use crate::{diagnostic::Diagnostic, lexer::lex, parser::parse, typeck::check};
+
+    fn try_build(src: &str) -> Result<String, Diagnostic> {
+        let (tokens, diagnostics) = lex(src);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let tokens = tokens.into_iter().filter(|t| !t.token.is_trivia()).collect();
+        let (mut ast, diagnostics) = parse(tokens);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let diagnostics = check(&mut ast);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        compile(ast)
+    }
+    fn build(src: &str) -> String {
+        try_build(src).unwrap()
+    }
+
+    #[test]
+    fn break_and_continue_find_their_loop() {
+        let wat = build("
+fn main() {
+    let mut i = 0;
+    'outer: while i < 10 {
+        i += 1;
+        'inner: while true {
+            if i == 2 { continue 'outer; }
+            if i == 3 { break 'inner; }
+            if i == 4 { continue; }
+            break 'outer;
+        }
+    }
+}
+");
+        // the outer loop is numbered 0 and the inner one 1
+        for jump in ["(br $cont_0)", "(br $brk_1)", "(br $cont_1)", "(br $brk_0)"] {
+            assert!(wat.contains(jump), "{} in {}", jump, wat);
+        }
+    }
+
+    const U8_RANGE: &str = r#"
+import "std.wat";
+
+fn main() {
+    let mut n: u32 = 0;
+    for i in 0u8..=255 {
+        n += 1;
+    }
+    if n == 256 { println("256"); }
+}
+"#;
+
+    #[test]
+    fn inclusive_ranges_stop_at_the_end_of_the_type() {
+        // `i` can't go past 255 to fail the `<=` check, so the loop ends before incrementing it
+        let wat = build(U8_RANGE);
+        let end = wat.find("(i32.eq)\n(br_if $brk_0)").expect(&wat);
+        assert!(end < wat.find("(local.get $i)\n(i32.const 1)\n(i32.add)\n(local.set $i)").expect(&wat));
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn inclusive_ranges_stop_at_the_end_of_the_type_at_runtime() {
+        assert_eq!(run(U8_RANGE, "u8_range"), "256");
+    }
+
+    #[test]
+    fn break_and_continue_need_a_loop() {
+        let err = try_build("fn main() { while true { break 'outer; } }").unwrap_err();
+        assert_eq!(err.msg, "use of undeclared label `'outer`");
+        let err = try_build("fn main() { if true { break; } }").unwrap_err();
+        assert_eq!(err.msg, "`break` outside of a loop");
+        let err = try_build("fn main() { continue; }").unwrap_err();
+        assert_eq!(err.msg, "`continue` outside of a loop");
+        // a label only applies inside its own loop
+        let err = try_build("fn main() { 'a: while true {} while true { continue 'a; } }").unwrap_err();
+        assert_eq!(err.msg, "use of undeclared label `'a`");
+    }
+
+    /// the wat of the function `name` in a compiled module
+    fn func<'a>(wat: &'a str, name: &str) -> &'a str {
+        let start = wat.find(&format!("(func ${} ", name)).unwrap();
+        let end = wat[start + 1..].find("(func ").map_or(wat.len(), |i| start + 1 + i);
+        &wat[start..end]
+    }
+
+    #[test]
+    fn signedness_picks_the_instruction() {
+        let wat = build("
+fn signed(a: i32, b: i32): bool { a / b >> 1 < b }
+fn unsigned(a: u32, b: u32): bool { a / b >> 1 < b }
+fn add_i8(a: i8): i8 { a + 1 }
+fn add_u8(a: u8): u8 { a + 1 }
+");
+        let (signed, unsigned) = (func(&wat, "signed"), func(&wat, "unsigned"));
+        for instr in ["div", "shr", "lt"] {
+            assert!(signed.contains(&format!("(i32.{}_s)", instr)), "{}", signed);
+            assert!(!signed.contains(&format!("(i32.{}_u)", instr)), "{}", signed);
+            assert!(unsigned.contains(&format!("(i32.{}_u)", instr)), "{}", unsigned);
+            assert!(!unsigned.contains(&format!("(i32.{}_s)", instr)), "{}", unsigned);
+        }
+        // 8 bit results are sign extended or masked back into range after arithmetic
+        assert!(func(&wat, "add_i8").contains("(i32.add)\n(i32.extend8_s)"), "{}", wat);
+        assert!(func(&wat, "add_u8").contains("(i32.add)\n(i32.const 255)\n(i32.and)"), "{}", wat);
+    }
+
+    /// a program taking the branch for `x` of a 4-way `else if` chain
+    fn chain(x: i32) -> String {
+        format!(r#"
+import "std.wat";
+
+fn main() {{
+    let x = {};
+    if x == 0 {{
+        println("zero");
+    }} else if x == 1 {{
+        println("one");
+    }} else if x == 2 {{
+        println("two");
+    }} else {{
+        println("many");
+    }}
+}}
+"#, x)
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let wat = build(r#"
+import "std.wat";
+
+/// documented, with a /* that isn't a block comment
+fn main() {
+    // println("line");
+    /* println("block"); /* nested */ println("still a comment"); */
+    println("code"); //// not a doc comment
+}
+"#);
+        assert_eq!(wat.matches("(call $println)").count(), 1, "{}", wat);
+    }
+
+    #[test]
+    fn else_if_chain_compiles_every_branch() {
+        let wat = build(&chain(0));
+        assert_eq!(wat.matches("(call $println)").count(), 4, "{}", wat);
+        // each `else if` is an `if` nested in the `else` of the one before
+        assert_eq!(wat.matches("(if\n").count(), 3);
+        assert_eq!(wat.matches("(else\n").count(), 3);
+    }
+
+    const BLOCKS: &str = r#"
+import "std.wat";
+
+fn main() {
+    let b = { 0 };
+    let s = {
+        let b = 40;
+        b + 2
+    };
+    {
+        let b = 1;
+        if b == 1 { println("a"); }
+    }
+    if b == 0 && s == 42 { println("b"); }
+}
+"#;
+
+    #[test]
+    fn blocks_leave_their_value_on_the_stack() {
+        let wat = build(BLOCKS);
+        assert_eq!(wat.matches("(block (result i32)\n").count(), 2, "{}", wat);
+        // the block used as a statement has no value
+        assert_eq!(wat.matches("(block\n").count(), 1, "{}", wat);
+        // its `b` shadows the outer one instead of overwriting it
+        assert!(wat.contains("(local $b_1 i32)") && wat.contains("(local $b_2 i32)"), "{}", wat);
+    }
+
+    const STRUCT_COPIES: &str = r#"
+import "std.wat";
+
+struct Inner { v: u8 }
+struct P { x: i32, z: u8, inner: Inner }
+
+fn bump(p: P): P {
+    let mut q = p;
+    q.z += 5;
+    q
+}
+
+fn main() {
+    let p = P { x: 1, z: 0, inner: Inner { v: 3 } };
+    let mut q = p;
+    q.z += 250;
+    q.inner.v = 9;
+    if p.z == 0 && p.inner.v == 3 { println("a"); }
+    let r = bump(p);
+    if p.z == 0 && r.z == 5 { println("b"); }
+    let mut s = P { x: 2, z: 1, inner: p.inner };
+    s.inner.v = 7;
+    s = p;
+    s.x = 100;
+    if p.x == 1 && p.inner.v == 3 { println("c"); }
+}
+"#;
+
+    #[test]
+    fn structs_are_copied_when_bound() {
+        let wat = build(STRUCT_COPIES);
+        // `q`, the argument of `bump`, `p.inner` in the literal and `s = p`, but not the literals or `bump(p)`
+        assert_eq!(wat.matches("(call $__copy_P)").count(), 4, "{}", wat);
+        assert_eq!(wat.matches("(call $__copy_Inner)").count(), 1, "{}", wat);
+        // copying a `P` copies its `Inner` too
+        let copy = func(&wat, "__copy_P");
+        assert!(copy.contains("(call $__copy_Inner (i32.load offset=8 (local.get $new)))"), "{}", copy);
+    }
+
+    #[test]
+    fn the_heap_has_a_memory_without_imports() {
+        let wat = build("struct P { x: i32 } fn main() { let p = P { x: 1 }; }");
+        assert!(wat.contains("(call $__alloc)"), "{}", wat);
+        assert!(wat.contains("(memory (export \"memory\") 1)"), "{}", wat);
+        // std.wat already declares one
+        let wat = build("import \"std.wat\"; struct P { x: i32 } fn main() { let p = P { x: 1 }; }");
+        assert_eq!(wat.matches("(memory 1)").count(), 1, "{}", wat);
+        assert!(!wat.contains("(memory (export"), "{}", wat);
+    }
+
+    /// runs the program with wasmtime and returns what it printed; the tests
+    /// using it are `#[ignore]`d, run them with `cargo test -- --ignored`
+    fn run(src: &str, name: &str) -> String {
+        if Command::new("wasmtime").arg("--version").output().is_err() {
+            panic!("wasmtime is not installed");
+        }
+        let path = env::temp_dir().join(format!("plang_{}.wat", name));
+        fs::write(&path, build(src)).unwrap();
+        let output = Command::new("wasmtime").arg(&path).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn else_if_chain_takes_every_branch_at_runtime() {
+        for (x, expected) in ["zero", "one", "two", "many", "many"].iter().enumerate() {
+            let output = run(&chain(x as i32), &format!("else_if_{}", x));
+            assert_eq!(output.trim(), *expected, "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn logical_operators_only_evaluate_the_rhs_when_needed() {
+        let wat = build("
+fn side(): bool { true }
+fn and(a: bool): bool { a && side() }
+fn or(a: bool): bool { a || side() }
+");
+        // the call is in the branch that runs when `a` doesn't decide the result on its own
+        let and = func(&wat, "and");
+        assert!(and.contains("(local.get $a)\n(if (result i32)\n(then\n(call $side)\n)\n(else\n(i32.const 0)\n)\n)"), "{}", and);
+        let or = func(&wat, "or");
+        assert!(or.contains("(local.get $a)\n(if (result i32)\n(then\n(i32.const 1)\n)\n(else\n(call $side)\n)\n)"), "{}", or);
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn logical_operators_short_circuit() {
+        let src = r#"
+import "std.wat";
+
+fn side(): bool {
+    println("x");
+    true
+}
+
+fn main() {
+    if false && side() { println("a"); }
+    if true || side() { println("b"); }
+    if true && side() { println("c"); }
+}
+"#;
+        assert_eq!(run(src, "short_circuit"), "bxc");
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn floats_cast_and_print() {
+        let src = r#"
+import "std.wat";
+
+fn main() {
+    let n = 7;
+    print_f64(n as f64 / -3.0);
+    println(" ");
+    print_f32(2.5);
+    println(" ");
+    let big = 300.7 as u8;
+    let nan = (0.0 / 0.0) as i32;
+    print_f32((big as i32 + nan) as f32);
+}
+"#;
+        assert_eq!(run(src, "floats"), "-2.333333 2.5 255");
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn floats_too_large_for_an_i64_print_with_an_exponent() {
+        let src = r#"
+import "std.wat";
+
+fn main() {
+    print_f64(100000000000000000000000.0);
+    println(" ");
+    print_f64(-18446744073709551616.0);
+    println(" ");
+    print_f64(18446744073709549568.0);
+    println(" ");
+    print_f32(340000000000000000000000000000000000000.0);
+}
+"#;
+        assert_eq!(run(src, "large_floats"), "1e23 -1.844674e19 18446744073709549568 3.4e38");
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn strings_are_escaped_and_stored_as_utf8() {
+        let src = r##"
+import "std.wat";
+
+fn main() {
+    println("a\tb\n\"\\\u{e9}");
+    println(r"\n");
+    println(r#"say "hi""#);
+    println("
+two\
+    lines");
+}
+"##;
+        assert_eq!(run(src, "strings"), "a\tb\n\"\\\u{e9}\\nsay \"hi\"\ntwolines");
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn chars_index_and_push_onto_strings() {
+        let src = r#"
+import "std.wat";
+
+fn main() {
+    let s = "hé😀";
+    let mut t = "";
+    'outer: for i in 0..3 {
+        t += s[2 - i];
+    }
+    let b: u8 = 33;
+    t += b as char;
+    t = t + '\n';
+    println(t);
+    if s[1] == '\u{e9}' && s[2] as i32 == 128512 {
+        println(s);
+    }
+}
+"#;
+        assert_eq!(run(src, "chars"), "😀éh!\nhé😀");
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn blocks_leave_their_value_on_the_stack_at_runtime() {
+        assert_eq!(run(BLOCKS, "blocks"), "ab");
+    }
 
-fn indent(amount: i32) -> String {
-    let mut out = String::new();
-    for _ in 0..amount {
-        out.push_str("    ");
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn structs_are_copied_when_bound_at_runtime() {
+        assert_eq!(run(STRUCT_COPIES, "struct_copies"), "abc");
     }
-    out
 }
@@ -0,0 +1,236 @@
+use std::{error::Error, fmt::Display};
+
+use crate::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+/// a message attached to a span. secondary labels point at something related
+/// to the diagnostic, e.g. "function `foo` defined here"
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub msg: String,
+    /// primary labels annotate the span of the diagnostic itself
+    pub primary: bool,
+}
+
+/// an error, warning or note produced by the lexer, parser or compiler.
+/// use `render` to print it along with the source it points at
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub msg: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    /// trailing `note: ...` and `help: ...` messages
+    pub notes: Vec<SubDiagnostic>,
+}
+
+/// a note or help attached to a diagnostic. one with a span of its own is
+/// rendered with the source it points at, after the plain ones
+#[derive(Debug, Clone)]
+pub struct SubDiagnostic {
+    pub severity: Severity,
+    pub msg: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn new<T: ToString>(severity: Severity, msg: T, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity,
+            msg: msg.to_string(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+    pub fn error<T: ToString>(msg: T, span: Span) -> Diagnostic {
+        Diagnostic::new(Severity::Error, msg, span)
+    }
+    pub fn warning<T: ToString>(msg: T, span: Span) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, msg, span)
+    }
+    /// text printed next to the carets under the diagnostic's own span
+    pub fn with_span_label<T: ToString>(mut self, msg: T) -> Diagnostic {
+        self.labels.push(Label {
+            span: self.span,
+            msg: msg.to_string(),
+            primary: true,
+        });
+        self
+    }
+    pub fn with_label<T: ToString>(mut self, span: Span, msg: T) -> Diagnostic {
+        self.labels.push(Label {
+            span,
+            msg: msg.to_string(),
+            primary: false,
+        });
+        self
+    }
+    fn with_sub<T: ToString>(mut self, severity: Severity, msg: T, span: Option<Span>) -> Diagnostic {
+        self.notes.push(SubDiagnostic {
+            severity,
+            msg: msg.to_string(),
+            span,
+        });
+        self
+    }
+    pub fn with_note<T: ToString>(self, msg: T) -> Diagnostic {
+        self.with_sub(Severity::Note, msg, None)
+    }
+    /// a note pointing at something elsewhere in the source, like the
+    /// definition of the function a call refers to
+    pub fn with_span_note<T: ToString>(self, span: Span, msg: T) -> Diagnostic {
+        self.with_sub(Severity::Note, msg, Some(span))
+    }
+    pub fn with_help<T: ToString>(self, msg: T) -> Diagnostic {
+        self.with_sub(Severity::Help, msg, None)
+    }
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+    /// renders the diagnostic rustc style:
+    /// ```text
+    /// error: unknown identifyer "foo"
+    ///  --> test.plang:2:5
+    ///   |
+    /// 2 |     foo();
+    ///   |     ^^^ not found in this scope
+    /// ```
+    pub fn render(&self, src: &str, path: &str) -> String {
+        let lines = src.lines().collect::<Vec<_>>();
+        let mut marks = Vec::new();
+        if !self.labels.iter().any(|l| l.primary) {
+            marks.push((self.span, '^', None));
+        }
+        for label in &self.labels {
+            let mark = if label.primary { '^' } else { '-' };
+            marks.push((label.span, mark, Some(label.msg.clone())));
+        }
+        marks.sort_by_key(|(span, _, _)| (span.line, span.col));
+
+        let gutter = marks.iter().map(|(s, _, _)| s.line).max().unwrap_or(1).to_string().len();
+        let pad = " ".repeat(gutter);
+
+        let mut out = format!("{}: {}\n", self.severity, self.msg);
+        out.push_str(&format!("{}--> {}:{}:{}\n", pad, path, self.span.line, self.span.col));
+        out.push_str(&format!("{} |\n", pad));
+        let mut last_line = None;
+        for (span, mark, msg) in marks {
+            let Some(line) = lines.get((span.line as usize).wrapping_sub(1)) else {
+                continue;
+            };
+            if let Some(last) = last_line {
+                if span.line > last + 1 {
+                    out.push_str("...\n");
+                }
+            }
+            if last_line != Some(span.line) {
+                out.push_str(&format!("{:>gutter$} | {}\n", span.line, line));
+            }
+            last_line = Some(span.line);
+
+            // keep tabs so the carets line up with the source line above them
+            let indent = line
+                .chars()
+                .take(span.col.saturating_sub(1) as usize)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+            let width = if span.end_line == span.line && span.end_col > span.col {
+                span.end_col - span.col
+            } else {
+                (line.chars().count() as u32 + 1).saturating_sub(span.col).max(1)
+            };
+            out.push_str(&format!("{} | {}{}", pad, indent, mark.to_string().repeat(width as usize)));
+            if let Some(msg) = msg {
+                out.push(' ');
+                out.push_str(&msg);
+            }
+            out.push('\n');
+        }
+        let (spanned, plain): (Vec<_>, Vec<_>) = self.notes.iter().partition(|n| n.span.is_some());
+        if !plain.is_empty() {
+            out.push_str(&format!("{} |\n", pad));
+        }
+        for note in plain {
+            out.push_str(&format!("{} = {}: {}\n", pad, note.severity, note.msg));
+        }
+        for note in spanned {
+            out.push_str(&Diagnostic::new(note.severity, &note.msg, note.span.unwrap()).render(src, path));
+        }
+        out
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+            Severity::Help => write!(f, "help"),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} on line {} column {}", self.severity, self.msg, self.span.line, self.span.col)
+    }
+}
+
+impl Error for Diagnostic {}
+
+#[cfg(test)]
+mod tests {
+    use super::Diagnostic;
+    use crate::span::Span;
+
+    /// a span of `len` chars on one line
+    fn span(line: u32, col: u32, len: u32) -> Span {
+        Span {
+            line,
+            col,
+            end_line: line,
+            end_col: col + len,
+            ..Span::default()
+        }
+    }
+
+    #[test]
+    fn render_shows_labels_notes_and_helps() {
+        let src = "fn add(a: i32) {}\n\nfn main() {\n    add(1, 2);\n}\n";
+        let diagnostic = Diagnostic::error("`add` takes 1 argument but 2 were supplied", span(4, 5, 3))
+            .with_span_label("called with 2 arguments")
+            .with_label(span(1, 1, 14), "function defined here")
+            .with_span_note(span(1, 8, 6), "the only parameter")
+            .with_note("a plain note")
+            .with_help("remove the extra argument");
+        let expected = "\
+error: `add` takes 1 argument but 2 were supplied
+ --> test.plang:4:5
+  |
+1 | fn add(a: i32) {}
+  | -------------- function defined here
+...
+4 |     add(1, 2);
+  |     ^^^ called with 2 arguments
+  |
+  = note: a plain note
+  = help: remove the extra argument
+note: the only parameter
+ --> test.plang:1:8
+  |
+1 | fn add(a: i32) {}
+  |        ^^^^^^
+";
+        assert_eq!(diagnostic.render(src, "test.plang"), expected);
+    }
+}
@@ -1,38 +1,48 @@
 use std::str::Chars;
 
-use crate::token::{Number, Token};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+use crate::token::{Number, SpannedToken, Token};
 
 
-pub fn lex<T: ToString>(input: T) -> Vec<Token>{
+pub fn lex<T: ToString>(input: T) -> (Vec<SpannedToken>, Vec<Diagnostic>) {
     let input = input.to_string();
     let mut input = input.chars();
     let cur_char = '\0';
-    let peek_char = input.next().unwrap();
+    let peek_char = input.next().unwrap_or('\0');
     let mut lexer = Lexer {
         input,
         cur_char,
         peek_char,
+        cur_pos: (0, 1, 1),
+        peek_pos: (0, 1, 1),
+        diagnostics: Vec::new(),
     };
 
-    lexer.lex()
+    let tokens = lexer.lex();
+    (tokens, lexer.diagnostics)
 }
 
 fn get_keyword(word: String) -> Token {
     match word.as_str() {
         "let" => Token::Let,
+        "mut" => Token::Mut,
         "fn" => Token::Fn,
         "for" => Token::For,
+        "in" => Token::In,
         "while" => Token::While,
         "if" => Token::If,
         "else" => Token::Else,
         "struct" => Token::Struct,
         "enum" => Token::Enum,
+        "match" => Token::Match,
         "return" => Token::Return,
         "break" => Token::Break,
         "continue" => Token::Continue,
         "true" => Token::True,
         "false" => Token::False,
         "import" => Token::Import,
+        "as" => Token::As,
         _ => Token::Ident(word),
     }
 }
@@ -41,22 +51,52 @@ struct Lexer<'a> {
     input: Chars<'a>,
     cur_char: char,
     peek_char: char,
+    /// (byte offset, line, column) of `cur_char`
+    cur_pos: (usize, u32, u32),
+    /// (byte offset, line, column) of `peek_char`
+    peek_pos: (usize, u32, u32),
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> Lexer<'a> {
-    fn lex(&mut self) -> Vec<Token> {
+    fn lex(&mut self) -> Vec<SpannedToken> {
         let mut tokens = Vec::new();
-        match self.advance() {
-            Some(_) => {
-                let token = self.next_token();
-                tokens.push(token);
-                tokens.append(&mut self.lex());
-                tokens
-            }
-            None => {
-                tokens.push(Token::Eof);
-                tokens
+        while self.advance().is_some() {
+            let start = self.cur_pos;
+            let token = self.next_token();
+            let span = self.span_from(start);
+            if token == Token::Elegal {
+                self.diagnostics.push(Diagnostic::error(format!("unexpected character `{}`", self.cur_char), span));
             }
+            tokens.push(SpannedToken {
+                token,
+                span,
+            });
+        }
+        tokens.push(SpannedToken {
+            token: Token::Eof,
+            span: self.span_from(self.peek_pos),
+        });
+        tokens
+    }
+    /// the span from `start` up to and including `cur_char`
+    fn span_from(&self, start: (usize, u32, u32)) -> Span {
+        Span {
+            start: start.0,
+            end: self.peek_pos.0.max(start.0),
+            line: start.1,
+            col: start.2,
+            end_line: self.peek_pos.1.max(start.1),
+            end_col: self.peek_pos.2,
+        }
+    }
+    /// consumes `peek_char` if it is `next` and returns `two`, otherwise returns `one`
+    fn either(&mut self, next: char, two: Token, one: Token) -> Token {
+        if self.peek_char == next {
+            self.advance();
+            two
+        } else {
+            one
         }
     }
     fn next_token(&mut self) -> Token {
@@ -65,52 +105,45 @@ impl<'a> Lexer<'a> {
             ')' => Token::RPeren,
             '{' => Token::LBrace,
             '}' => Token::RBrace,
-            '+' => Token::Plus,
-            '-' => Token::Minus,
-            '*' => Token::Star,
-            '/' => Token::Slash,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '+' => self.either('=', Token::PlusEq, Token::Plus),
+            '-' => self.either('=', Token::MinusEq, Token::Minus),
+            '*' => self.either('=', Token::StarEq, Token::Star),
+            '/' if self.peek_char == '/' => self.lex_line_comment(),
+            '/' if self.peek_char == '*' => self.lex_block_comment(),
+            '/' => self.either('=', Token::SlashEq, Token::Slash),
+            '%' => Token::Percent,
             '^' => Token::Carret,
+            '~' => Token::Tilde,
+            '&' => self.either('&', Token::AndAnd, Token::Elegal),
+            '|' => self.either('|', Token::OrOr, Token::Elegal),
             ',' => Token::Coma,
-            '.' => Token::Dot,
-            '=' => {
-                match self.peek_char {
-                    '=' => Token::EqTo,
-                    _ => Token::Eq
-                }
-            }
-            '!' => {
-                match self.peek_char {
-                    '=' => Token::NotEq,
-                    _ => Token::Not
-                }
-            }
-            '>' => {
-                match self.peek_char {
-                    '=' => Token::GtEq,
-                    _ => Token::Gt
-                }
-            }
-            '<' => {
-                match self.peek_char {
-                    '=' => Token::LtEq,
-                    _ => Token::Lt
-                }
+            '.' if self.peek_char == '.' => {
+                self.advance();
+                self.either('=', Token::DotDotEq, Token::DotDot)
             }
-            ':' => Token::Colin,
+            '.' => Token::Dot,
+            '=' if self.peek_char == '>' => self.either('>', Token::FatArrow, Token::Eq),
+            '=' => self.either('=', Token::EqTo, Token::Eq),
+            '!' => self.either('=', Token::NotEq, Token::Not),
+            '>' if self.peek_char == '>' => self.either('>', Token::Shr, Token::Gt),
+            '>' => self.either('=', Token::GtEq, Token::Gt),
+            '<' if self.peek_char == '<' => self.either('<', Token::Shl, Token::Lt),
+            '<' => self.either('=', Token::LtEq, Token::Lt),
+            ':' => self.either(':', Token::ColinColin, Token::Colin),
             ';' => Token::SemiColin,
-            '\'' => Token::Quote,
-            '"' => {
-                self.advance();
-                let str = self.advance_while(|s| s != '"');
-                self.advance();
-                Token::String(str)
+            '\'' => self.lex_quote(),
+            '"' => self.lex_string(),
+            'r' if self.peek_char == '"' || self.peek_char == '#' && self.input.clone().find(|c| *c != '#') == Some('"') => {
+                self.lex_raw_string()
             }
             '\n' => Token::NewLine,
             char => match char {
                 c if c.is_ascii_digit() => {
                     Token::Number(self.lex_number())
                 }
-                c if c.is_ascii_alphabetic() => {
+                c if c.is_ascii_alphabetic() || c == '_' => {
                     let word = self.advance_while(|c| c.is_ascii_alphanumeric() || c == '_');
                     get_keyword(word)
                 }
@@ -123,15 +156,267 @@ impl<'a> Lexer<'a> {
             }
         }
     }
+    /// a char literal like `'a'` or `'\n'`, or a loop label like `'outer`.
+    /// a label never has a closing quote right after its first char
+    fn lex_quote(&mut self) -> Token {
+        let start = self.cur_pos;
+        let closed = self.input.clone().next() == Some('\'');
+        if self.peek_char == '\\' || closed && self.peek_char != '\n' {
+            return self.lex_char(start);
+        }
+        if self.peek_char == '\'' {
+            self.advance();
+            self.diagnostics.push(Diagnostic::error("empty character literal", self.span_from(start))
+                .with_span_label("empty character literal"));
+            return Token::Char('\0');
+        }
+        if self.peek_char.is_ascii_alphabetic() || self.peek_char == '_' {
+            self.advance();
+            let label = self.advance_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            if self.peek_char == '\'' {
+                self.advance();
+                self.diagnostics.push(Diagnostic::error("character literal may only contain one codepoint", self.span_from(start))
+                    .with_help(format!("if you meant to write a string literal, use double quotes: `\"{}\"`", label)));
+                return Token::String(label);
+            }
+            return Token::Label(label);
+        }
+        self.diagnostics.push(Diagnostic::error("unterminated character literal", self.span_from(start))
+            .with_span_label("needs a closing `'`"));
+        Token::Char('\0')
+    }
+    /// `'a'` or `'\n'`, with `cur_char` on the opening quote
+    fn lex_char(&mut self, start: (usize, u32, u32)) -> Token {
+        self.advance();
+        let escaped = self.cur_char == '\\';
+        let c = match self.cur_char {
+            '\\' if self.peek_char == '\n' => {
+                self.diagnostics.push(Diagnostic::error("unterminated character literal", self.span_from(start))
+                    .with_span_label("needs a closing `'`"));
+                return Token::Char('\0');
+            }
+            '\\' => self.lex_escape().unwrap_or('\0'),
+            c => c,
+        };
+        if self.peek_char != '\'' {
+            self.diagnostics.push(Diagnostic::error("unterminated character literal", self.span_from(start))
+                .with_span_label("needs a closing `'`"));
+            return Token::Char(c);
+        }
+        self.advance();
+        if c == '\'' && !escaped {
+            self.diagnostics.push(Diagnostic::error("character constant must be escaped: `'`", self.span_from(start))
+                .with_help("escape the character: `'\\''`"));
+        }
+        Token::Char(c)
+    }
+    /// `"..."`, which can span lines and has escapes like `\n` and `\u{1F600}`
+    fn lex_string(&mut self) -> Token {
+        let start = self.cur_pos;
+        let mut str = String::new();
+        loop {
+            if self.advance().is_none() {
+                self.unterminated("double quote string", start, 1);
+                return Token::String(str);
+            }
+            match self.cur_char {
+                '"' => return Token::String(str),
+                '\\' => {
+                    if let Some(c) = self.lex_escape() {
+                        str.push(c);
+                    }
+                }
+                c => str.push(c),
+            }
+        }
+    }
+    /// the char an escape like `\n` stands for, with `cur_char` on the `\`.
+    /// `None` for a `\` at the end of a line, which skips the line break and
+    /// the indentation after it, or for an escape that isn't valid
+    fn lex_escape(&mut self) -> Option<char> {
+        let start = self.cur_pos;
+        self.advance()?;
+        let c = match self.cur_char {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            '\n' => {
+                while self.peek_char.is_whitespace() && self.advance().is_some() {}
+                return None;
+            }
+            'u' => return self.lex_unicode_escape(start),
+            c => {
+                self.diagnostics.push(Diagnostic::error(format!("unknown character escape: `{}`", c), self.span_from(start))
+                    .with_span_label("unknown character escape")
+                    .with_help("the escapes are `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\\"`, `\\'` and `\\u{...}`"));
+                return None;
+            }
+        };
+        Some(c)
+    }
+    /// `\u{1F600}`, with `cur_char` on the `u`
+    fn lex_unicode_escape(&mut self, start: (usize, u32, u32)) -> Option<char> {
+        if self.peek_char != '{' {
+            self.diagnostics.push(Diagnostic::error("incorrect unicode escape sequence", self.span_from(start))
+                .with_help("format unicode escapes as `\\u{1F600}`"));
+            return None;
+        }
+        self.advance();
+        let mut hex = String::new();
+        while self.peek_char != '}' && self.peek_char != '"' && self.peek_char != '\0' {
+            self.advance();
+            hex.push(self.cur_char);
+        }
+        if self.peek_char != '}' {
+            self.diagnostics.push(Diagnostic::error("unterminated unicode escape", self.span_from(start))
+                .with_span_label("missing a closing `}`"));
+            return None;
+        }
+        self.advance();
+        let err = match u32::from_str_radix(&hex, 16) {
+            _ if hex.is_empty() || hex.len() > 6 => "a unicode escape has 1 to 6 hex digits",
+            Err(_) => "a unicode escape can only have hex digits",
+            Ok(n) => match char::from_u32(n) {
+                Some(c) => return Some(c),
+                None if (0xD800..0xE000).contains(&n) => "a unicode escape can't be a surrogate",
+                None => "a unicode escape can't be above 10FFFF",
+            },
+        };
+        self.diagnostics.push(Diagnostic::error("invalid unicode character escape", self.span_from(start))
+            .with_span_label(err));
+        None
+    }
+    /// `r"..."` or `r#"..."#` with any number of `#`, which has no escapes.
+    /// the `#`s let the string have `"` in it
+    fn lex_raw_string(&mut self) -> Token {
+        let start = self.cur_pos;
+        let mut hashes = 0;
+        while self.advance() == Some('#') {
+            hashes += 1;
+        }
+        let mut str = String::new();
+        loop {
+            if self.advance().is_none() {
+                self.unterminated("raw string", start, hashes + 2);
+                return Token::String(str);
+            }
+            let closing = hashes == 0
+                || self.peek_char == '#' && self.input.clone().take(hashes - 1).filter(|c| *c == '#').count() == hashes - 1;
+            if self.cur_char == '"' && closing {
+                for _ in 0..hashes {
+                    self.advance();
+                }
+                return Token::String(str);
+            }
+            str.push(self.cur_char);
+        }
+    }
+    /// reports a string that reaches the end of the file, pointing at its first `len` chars
+    fn unterminated(&mut self, what: &str, start: (usize, u32, u32), len: usize) {
+        let span = Span {
+            start: start.0,
+            end: start.0 + len,
+            line: start.1,
+            col: start.2,
+            end_line: start.1,
+            end_col: start.2 + len as u32,
+        };
+        self.diagnostics.push(Diagnostic::error(format!("unterminated {}", what), span)
+            .with_span_label("string starts here")
+            .with_note("it has no closing quote before the end of the file"));
+    }
+    /// `// ...` up to the end of the line, or `/// ...` for a doc comment
+    fn lex_line_comment(&mut self) -> Token {
+        let text = self.advance_while(|c| c != '\n');
+        // `////` is a normal comment, like in rust
+        match text.strip_prefix("///") {
+            Some(doc) if !doc.starts_with('/') => Token::DocComment(doc.strip_prefix(' ').unwrap_or(doc).to_string()),
+            _ => Token::Comment(text),
+        }
+    }
+    /// `/* ... */`, which can have other block comments in it
+    fn lex_block_comment(&mut self) -> Token {
+        let start = self.cur_pos;
+        self.advance();
+        let opening = self.span_from(start);
+        let mut text = String::from("/*");
+        let mut depth = 1;
+        while self.advance().is_some() {
+            text.push(self.cur_char);
+            match (self.cur_char, self.peek_char) {
+                ('/', '*') => depth += 1,
+                ('*', '/') => depth -= 1,
+                _ => continue,
+            }
+            // the second char of `/*` or `*/` can't start another one
+            self.advance();
+            text.push(self.cur_char);
+            if depth == 0 {
+                return Token::Comment(text);
+            }
+        }
+        self.diagnostics.push(Diagnostic::error("unterminated block comment", opening)
+            .with_span_label("comment starts here")
+            .with_note("block comments can be nested, every `/*` needs a `*/`"));
+        Token::Comment(text)
+    }
     fn lex_number(&mut self) -> Number {
-        let num = self.advance_while(|n| match n {
-            char if char.is_ascii_digit() || char == '.' => true,
-            _ => false
-        });
-        if num.contains('.') {
-            Number::F32(num.parse().unwrap())
-        } else {
-            Number::I32(num.parse().unwrap())
+        let start = self.cur_pos;
+        // `_` can be used to group digits, like `1_000_000`
+        let mut num = self.advance_while(|n| n.is_ascii_digit() || n == '_');
+        // the `.` in `0..n` belongs to the range
+        if self.peek_char == '.' && self.input.clone().next() != Some('.') {
+            self.advance();
+            num.push_str(&self.advance_while(|n| n.is_ascii_digit() || n == '_'));
+            // `1.2.3`, but not `1.2..3`
+            if self.peek_char == '.' && self.input.clone().next().is_some_and(|c| c.is_ascii_digit()) {
+                self.advance();
+                num.push_str(&self.advance_while(|n| n.is_ascii_digit() || n == '.'));
+                self.diagnostics.push(Diagnostic::error(format!("invalid number `{}`", num), self.span_from(start))
+                    .with_span_label("a number can only have one `.`"));
+                return Number::Float(0.0, None);
+            }
+        }
+        // a type right after the digits, like `10i64` or `2.5f64`
+        let mut suffix = None;
+        if self.peek_char.is_ascii_alphabetic() || self.peek_char == '_' {
+            let suffix_start = self.peek_pos;
+            self.advance();
+            let s = self.advance_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            let is_float = num.contains('.');
+            let valid = match s.as_str() {
+                "f32" | "f64" => true,
+                "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" => !is_float,
+                _ => false,
+            };
+            if !valid {
+                let (what, help) = if is_float {
+                    ("float", "the suffix must be `f32` or `f64`")
+                } else {
+                    ("number", "the suffix must be one of the number types, like `u8`, `i64` or `f32`")
+                };
+                self.diagnostics.push(Diagnostic::error(format!("invalid suffix `{}` for {} literal", s, what), self.span_from(suffix_start))
+                    .with_span_label(format!("invalid suffix `{}`", s))
+                    .with_help(help));
+            } else {
+                suffix = Some(s);
+            }
+        }
+        let num = num.replace('_', "");
+        if num.contains('.') || suffix.as_ref().is_some_and(|s| s.starts_with('f')) {
+            return Number::Float(num.parse().expect("digits with at most one `.` are a valid float"), suffix);
+        }
+        match num.parse() {
+            Ok(n) => Number::Int(n, suffix),
+            Err(_) => {
+                self.diagnostics.push(Diagnostic::error(format!("integer literal `{}` is too large", num), self.span_from(start))
+                    .with_span_label(format!("the largest integer is {}", u64::MAX)));
+                Number::Int(0, suffix)
+            }
         }
     }
     fn advance_while<F: Fn(char) -> bool>(&mut self, f: F) -> String {
@@ -146,16 +431,120 @@ impl<'a> Lexer<'a> {
             }
             res.push(self.cur_char);
         }
-        return res;
+        res
     }
     fn advance(&mut self) -> Option<char> {
         if self.peek_char == '\0' {
             return None
         }
         self.cur_char = self.peek_char;
+        self.cur_pos = self.peek_pos;
+        self.peek_pos = if self.cur_char == '\n' {
+            (self.cur_pos.0 + 1, self.cur_pos.1 + 1, 1)
+        } else {
+            (self.cur_pos.0 + self.cur_char.len_utf8(), self.cur_pos.1, self.cur_pos.2 + 1)
+        };
         self.peek_char = self.input.next().unwrap_or('\0');
         Some(self.cur_char)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::lex;
+    use crate::token::{Number, Token};
+
+    /// the tokens of `src` without whitespace, comments and the final `Eof`
+    fn tokens(src: &str) -> Vec<Token> {
+        let (tokens, diagnostics) = lex(src);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        tokens.into_iter().map(|t| t.token).filter(|t| !t.is_trivia() && *t != Token::Eof).collect()
+    }
+    /// the message, line, first and last column and label of each diagnostic from lexing `src`
+    fn diagnostics(src: &str) -> Vec<(String, u32, u32, u32, Option<String>)> {
+        lex(src).1.into_iter()
+            .map(|d| (d.msg, d.span.line, d.span.col, d.span.end_col, d.labels.first().map(|l| l.msg.clone())))
+            .collect()
+    }
+    fn string(s: &str) -> Token {
+        Token::String(s.to_string())
+    }
+
+    #[test]
+    fn a_number_has_at_most_one_point() {
+        let (_, diagnostics) = lex("1.2.3");
+        let [diagnostic] = diagnostics.as_slice() else {
+            panic!("expected one diagnostic, found {:?}", diagnostics);
+        };
+        assert_eq!(diagnostic.msg, "invalid number `1.2.3`");
+        assert_eq!((diagnostic.span.col, diagnostic.span.end_col), (1, 6));
+        // the second `.` starts a range
+        assert_eq!(tokens("1.2..3"), [
+            Token::Number(Number::Float(1.2, None)),
+            Token::DotDot,
+            Token::Number(Number::Int(3, None)),
+        ]);
+    }
 
+    #[test]
+    fn strings_decode_their_escapes() {
+        assert_eq!(tokens(r#""a\tb\r\n\0\"\\\'\u{e9}\u{1F600}""#), [string("a\tb\r\n\0\"\\'\u{e9}\u{1F600}")]);
+        // a `\` at the end of a line skips the line break and the indentation after it
+        assert_eq!(tokens("\"two\\\n    lines\""), [string("twolines")]);
+        assert_eq!(tokens("\"two\n  lines\""), [string("two\n  lines")]);
+    }
+
+    #[test]
+    fn raw_strings_have_no_escapes() {
+        assert_eq!(tokens(r#"r"\n\u{e9}""#), [string(r"\n\u{e9}")]);
+        assert_eq!(tokens(r##"r#"say "hi""#"##), [string(r#"say "hi""#)]);
+        // fewer `#`s than it was opened with don't close it
+        assert_eq!(tokens(r###"r##"a "# b"##"###), [string(r##"a "# b"##)]);
+    }
+
+    #[test]
+    fn bad_escapes_are_reported_where_they_are() {
+        let label = |l: &str| Some(l.to_string());
+        assert_eq!(diagnostics(r#""a\qb""#), [
+            (String::from("unknown character escape: `q`"), 1, 3, 5, label("unknown character escape")),
+        ]);
+        // the rest of the string is still read
+        assert_eq!(lex(r#""a\qb""#).0[0].token, string("ab"));
+        assert_eq!(diagnostics(r#""\u{D800}""#), [
+            (String::from("invalid unicode character escape"), 1, 2, 10, label("a unicode escape can't be a surrogate")),
+        ]);
+        assert_eq!(diagnostics(r#""\u{110000}""#), [
+            (String::from("invalid unicode character escape"), 1, 2, 12, label("a unicode escape can't be above 10FFFF")),
+        ]);
+        assert_eq!(diagnostics(r#""\u{12""#), [
+            (String::from("unterminated unicode escape"), 1, 2, 7, label("missing a closing `}`")),
+        ]);
+        assert_eq!(diagnostics(r#""\u12""#), [(String::from("incorrect unicode escape sequence"), 1, 2, 4, None)]);
+    }
+
+    #[test]
+    fn unterminated_strings_point_at_their_opening_quote() {
+        let starts_here = Some(String::from("string starts here"));
+        assert_eq!(diagnostics("fn f() {}\nlet s = \"abc;\n"), [
+            (String::from("unterminated double quote string"), 2, 9, 10, starts_here.clone()),
+        ]);
+        assert_eq!(diagnostics("let s = r#\"abc\"; }"), [
+            (String::from("unterminated raw string"), 1, 9, 12, starts_here),
+        ]);
+    }
+
+    #[test]
+    fn quotes_start_chars_or_labels() {
+        assert_eq!(tokens("'a'"), [Token::Char('a')]);
+        assert_eq!(tokens(r"'\u{1F600}'"), [Token::Char('\u{1F600}')]);
+        assert_eq!(tokens(r"'\''"), [Token::Char('\'')]);
+        // `loop` isn't a keyword, so it lexes as a name after the label
+        assert_eq!(tokens("'outer: loop"), [Token::Label(String::from("outer")), Token::Colin, Token::Ident(String::from("loop"))]);
+        assert_eq!(tokens("break 'a;"), [Token::Break, Token::Label(String::from("a")), Token::SemiColin]);
+        assert_eq!(tokens("'a: while"), [Token::Label(String::from("a")), Token::Colin, Token::While]);
+        let messages = |src| diagnostics(src).into_iter().map(|d| d.0).collect::<Vec<_>>();
+        assert_eq!(messages("''"), ["empty character literal"]);
+        assert_eq!(messages("'ab'"), ["character literal may only contain one codepoint"]);
+        assert_eq!(messages("'''"), ["character constant must be escaped: `'`"]);
+    }
+}
@@ -1,34 +1,183 @@
-use std::fs;
+use std::{env, fs, path::{Path, PathBuf}, process::{self, ExitCode}};
 
-use crate::{compiler::compile, parser::parse, wat::get_exports};
+use crate::{ast::Ast, compiler::compile, diagnostic::Diagnostic, parser::parse, token::{SpannedToken, Token}, typeck::check};
 
 mod lexer;
 mod token;
 mod parser;
+mod typeck;
 mod compiler;
 mod ast;
+mod diagnostic;
+mod span;
 mod wat;
 
-fn main() {
-    // let input = fs::read_to_string("./test.wat").unwrap();
-    // let exports = get_exports(input);
-    // for exp in exports {
-    //     println!("{:?}", exp);
-    // }
-    // return;
-
-    let input = fs::read_to_string("./test.plang").unwrap();
-    println!("{}", input);
-    let tokens = lexer::lex(input).into_iter().filter(|t| !t.is_whitespace()).collect();
-    println!("{:?}", tokens);
-    let ast = parse(tokens).inspect_err(|e| println!("{}", e)).unwrap();
-    println!("\nast:\n{:?}\n", ast);
-    for section in ast.clone() {
-        println!("{}", section);
+const USAGE: &str = "usage: plang <command> [options] <file>
+
+commands:
+    build <file> [-o <out>]         compile <file> to wat (defaults to <file> with a .wat extension)
+    run <file>                      compile <file> and run it with wasmtime
+    check <file>                    parse and compile <file> without writing any output
+    emit --tokens|--ast|--wat <file>  print an intermediate representation of <file>";
+
+enum Command {
+    Build { file: PathBuf, out: Option<PathBuf> },
+    Run { file: PathBuf },
+    Check { file: PathBuf },
+    Emit { file: PathBuf, what: Emit },
+}
+
+enum Emit {
+    Tokens,
+    Ast,
+    Wat,
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let command = match parse_args(&args) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run_command(command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
     }
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let (command, rest) = match args.split_first() {
+        Some((c, rest)) => (c.as_str(), rest),
+        None => return Err(String::from("no command given")),
+    };
+    let mut file = None;
+    let mut out = None;
+    let mut emit = None;
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-o" | "--out" if command == "build" => match rest.next() {
+                Some(o) => out = Some(PathBuf::from(o)),
+                None => return Err(format!("missing path after {}", arg)),
+            },
+            "--tokens" if command == "emit" => emit = Some(Emit::Tokens),
+            "--ast" if command == "emit" => emit = Some(Emit::Ast),
+            "--wat" if command == "emit" => emit = Some(Emit::Wat),
+            a if a.starts_with('-') => return Err(format!("unknown option {} for {}", a, command)),
+            a => {
+                if file.is_some() {
+                    return Err(format!("unexpected argument {}", a));
+                }
+                file = Some(PathBuf::from(a));
+            }
+        }
+    }
+    let file = file.ok_or_else(|| String::from("no input file given"))?;
+    match command {
+        "build" => Ok(Command::Build { file, out }),
+        "run" => Ok(Command::Run { file }),
+        "check" => Ok(Command::Check { file }),
+        "emit" => match emit {
+            Some(what) => Ok(Command::Emit { file, what }),
+            None => Err(String::from("emit needs one of --tokens, --ast or --wat")),
+        },
+        c => Err(format!("unknown command {}", c)),
+    }
+}
 
-    let wasm = compile(ast).unwrap();
-    println!("\n{}", wasm.clone());
+fn run_command(command: Command) -> Result<(), String> {
+    match command {
+        Command::Build { file, out } => {
+            let wat = build(&file)?;
+            let out = out.unwrap_or_else(|| file.with_extension("wat"));
+            fs::write(&out, wat).map_err(|e| format!("error: could not write {}: {}", out.display(), e))
+        }
+        Command::Run { file } => {
+            let wat = build(&file)?;
+            let stem = file.file_stem().unwrap_or(file.as_os_str());
+            let out = env::temp_dir().join(stem).with_extension("wat");
+            fs::write(&out, wat).map_err(|e| format!("error: could not write {}: {}", out.display(), e))?;
+            let status = process::Command::new("wasmtime")
+                .arg(&out)
+                .status()
+                .map_err(|e| format!("error: could not run wasmtime: {}", e))?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(format!("error: program exited with {}", status))
+            }
+        }
+        Command::Check { file } => build(&file).map(|_| ()),
+        Command::Emit { file, what } => {
+            match what {
+                Emit::Tokens => {
+                    for t in lex(&file, &read(&file)?)? {
+                        println!("{}: {:?}", t.span, t.token);
+                    }
+                }
+                Emit::Ast => {
+                    for section in parse_file(&file)?.1 {
+                        println!("{:#?}", section);
+                    }
+                }
+                Emit::Wat => println!("{}", build(&file)?),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn read(file: &Path) -> Result<String, String> {
+    fs::read_to_string(file).map_err(|e| format!("error: could not read {}: {}", file.display(), e))
+}
+
+/// prints warnings and notes straight away and renders any errors into the returned `Err`
+fn report(diagnostics: &[Diagnostic], src: &str, file: &Path) -> Result<(), String> {
+    let path = file.display().to_string();
+    let mut errors = Vec::new();
+    for diagnostic in diagnostics {
+        if diagnostic.is_error() {
+            errors.push(diagnostic.render(src, &path));
+        } else {
+            eprintln!("{}", diagnostic.render(src, &path));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+fn lex(file: &Path, src: &str) -> Result<Vec<SpannedToken>, String> {
+    let (tokens, diagnostics) = lexer::lex(src);
+    report(&diagnostics, src, file)?;
+    Ok(tokens.into_iter().filter(|t| !t.token.is_trivia()).collect())
+}
+
+fn parse_file(file: &Path) -> Result<(String, Vec<Ast>), String> {
+    let src = read(file)?;
+    let (tokens, mut diagnostics) = lexer::lex(&src);
+    // illegal characters are already reported by the lexer
+    let tokens = tokens
+        .into_iter()
+        .filter(|t| !t.token.is_trivia() && t.token != Token::Elegal)
+        .collect();
+    let (ast, parse_diagnostics) = parse(tokens);
+    diagnostics.extend(parse_diagnostics);
+    diagnostics.sort_by_key(|d| d.span.start);
+    report(&diagnostics, &src, file)?;
+    Ok((src, ast))
+}
 
-    let _ = fs::write("./out.wat", wasm);
+fn build(file: &Path) -> Result<String, String> {
+    let (src, mut ast) = parse_file(file)?;
+    report(&check(&mut ast), &src, file)?;
+    compile(ast).map_err(|e| report(&[e], &src, file).unwrap_err())
 }
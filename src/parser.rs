@@ -1,69 +1,143 @@
 use std::collections::{HashMap, HashSet};
-use std::error::Error;
-use std::fmt::Display;
 use std::fs;
 
-use crate::token::{Number, Token};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+use crate::token::{Number, SpannedToken, Token};
 use crate::ast::*;
 use crate::wat::get_exports;
 
-pub fn parse(tokens: Vec<Token>) -> Result<Vec<Ast>, ParserError> {
-    let cur_tok = tokens.first().unwrap().clone();
-    let peek_tok = tokens.get(1).unwrap().clone();
+/// parses as much of the program as it can. errors are collected instead of
+/// stopping the parse, so the returned ast may be missing the broken parts
+pub fn parse(tokens: Vec<SpannedToken>) -> (Vec<Ast>, Vec<Diagnostic>) {
     let mut parser = Parser {
         tokens,
-        cur_tok,
-        peek_tok,
-        idx: 1,
-        funcs: HashSet::new(),
+        cur_tok: Token::Eof,
+        cur_span: Span::default(),
+        peek_tok: Token::Eof,
+        peek_span: Span::default(),
+        prev_span: Span::default(),
+        idx: 0,
+        funcs: HashMap::new(),
         scopes: vec![HashSet::new()],
         types: HashSet::new(),
-        line: 1,
+        docs: Vec::new(),
+        peek_docs: Vec::new(),
+        no_struct_lit: false,
+        diagnostics: Vec::new(),
     };
+    // load the first token into peek_tok and then advance onto it
+    parser.skip_newlines();
+    parser.advance();
 
-    parser.types.insert(String::from("i32"));
-
-    return parser.parse()
-}
-
+    for tp in ["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64", "bool", "char"] {
+        parser.types.insert(String::from(tp));
+    }
 
-#[derive(Debug)]
-pub struct ParserError {
-    msg: String,
-    line: u32,
+    let program = parser.parse();
+    (program, parser.diagnostics)
 }
 
-impl Display for ParserError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} on line {}", self.msg, self.line)
-    }
-}
-impl Error for ParserError {}
+pub type ParserError = Diagnostic;
 
 struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     cur_tok: Token,
+    cur_span: Span,
     peek_tok: Token,
+    peek_span: Span,
+    /// span of the last token that was advanced past, used to find where a node ends
+    prev_span: Span,
     idx: usize,
-    funcs: HashSet<String>,
+    /// every known function and where it was defined
+    funcs: HashMap<String, Span>,
     scopes: Vec<HashSet<String>>,
     types: HashSet<String>,
-    line: u32,
+    /// the doc comments right before `cur_tok` and `peek_tok`, with where they are
+    docs: Vec<(String, Span)>,
+    peek_docs: Vec<(String, Span)>,
+    /// set while parsing the condition of an `if` or loop and the value of a `match`,
+    /// where a `{` starts the block instead of a struct literal
+    no_struct_lit: bool,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Parser {
-    fn parse(&mut self) -> Result<Vec<Ast>, ParserError>{
+    fn parse(&mut self) -> Vec<Ast> {
         let mut program = Vec::new();
         while ! self.cur_tok.is_eof() {
-            program.push(self.parse_section()?);
+            if let Some(section) = self.parse_section_or_recover() {
+                program.push(section);
+            }
+        }
+        self.warn_unused_docs();
+        program
+    }
+    /// parses a section, on error the error is recorded and the parser skips
+    /// ahead to the next place it can sensibly continue from
+    fn parse_section_or_recover(&mut self) -> Option<Ast> {
+        let idx = self.idx;
+        match self.parse_section() {
+            Ok(section) => Some(section),
+            Err(e) => {
+                self.diagnostics.push(e);
+                // always make progress so a token nothing can start with isn't reported forever.
+                // a `{` is skipped along with the rest of its group, otherwise its `}` would end the enclosing block
+                if self.idx == idx {
+                    self.skip_group();
+                }
+                self.synchronize();
+                None
+            }
+        }
+    }
+    /// skips tokens until the end of the current statement, the end of the
+    /// enclosing block or the start of something new. `{ }` and `( )` groups
+    /// are skipped as a whole
+    fn synchronize(&mut self) {
+        loop {
+            match self.cur_tok {
+                Token::Eof | Token::RBrace => return,
+                Token::SemiColin => {
+                    self.advance();
+                    return;
+                }
+                // a block ends the statement it belongs to
+                Token::LBrace => {
+                    self.skip_group();
+                    return;
+                }
+                Token::LPeren | Token::LBracket => self.skip_group(),
+                Token::Fn | Token::Struct | Token::Enum | Token::Import | Token::Let |
+                Token::If | Token::While | Token::For | Token::Return |
+                Token::Break | Token::Continue => return,
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+    /// skips the current token, or everything up to and including the matching
+    /// closing token if it opens a group
+    fn skip_group(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.cur_tok {
+                Token::Eof => return,
+                Token::LBrace | Token::LPeren | Token::LBracket => depth += 1,
+                Token::RBrace | Token::RPeren | Token::RBracket => depth -= 1,
+                _ => {}
+            }
+            self.advance();
+            if depth <= 0 {
+                return;
+            }
         }
-        Ok(program)
     }
     fn parse_block(&mut self) -> Result<Vec<Ast>, ParserError> {
 
         if self.cur_tok != Token::LBrace {
-            println!("{:?}", self.cur_tok);
-            return self.make_err("expected a block")
+            return self.make_err(format!("expected a block, found {}", self.cur_tok))
         }
         self.advance();
 
@@ -71,33 +145,131 @@ impl Parser {
          
         let mut block = Vec::new();
         while self.cur_tok != Token::RBrace && ! self.cur_tok.is_eof() {
-            block.push(self.parse_section()?);
+            if let Some(section) = self.parse_section_or_recover() {
+                block.push(section);
+            }
         }
+
+        self.scopes.pop();
+
         if self.cur_tok == Token::RBrace {
             self.advance();
         } else {
-            return self.make_err("expected right brace")
+            return self.make_err(format!("expected `}}`, found {}", self.cur_tok))
         }
 
-        self.scopes.pop();
-
-        return Ok(block);
+        Ok(block)
     }
     fn parse_section(&mut self) -> Result<Ast, ParserError> {
         match &self.cur_tok {
             Token::Fn => Ok(Ast::Statement(self.parse_fn_def()?)),
-            Token::Ident(_) => Ok(Ast::Expression(self.parse_expr()?)),
             Token::If => Ok(Ast::Expression(self.parse_if()?)),
+            Token::Match => Ok(Ast::Expression(self.parse_match()?)),
+            Token::While | Token::For | Token::Label(_) => Ok(Ast::Expression(self.parse_loop()?)),
+            Token::Break | Token::Continue => Ok(Ast::Statement(self.parse_break()?)),
             Token::Struct => Ok(Ast::Statement(self.parse_struct_def()?)),
+            Token::Enum => Ok(Ast::Statement(self.parse_enum_def()?)),
             Token::Import => Ok(Ast::Statement(self.parse_import()?)),
-            // Token::Let => Ok(Ast::Statement(self.parse_let()?)),
-            t => unreachable!("{:?} line: {}", t, self.line),
+            Token::Let => Ok(Ast::Statement(self.parse_let()?)),
+            Token::Return => Ok(Ast::Statement(self.parse_return()?)),
+            // blocks can only be nested in a function, one at the top level is an error
+            Token::LBrace if self.scopes.len() > 1 => Ok(Ast::Expression(self.parse_block_expr()?)),
+            t if t.can_start_expr() => self.parse_expr_statement(),
+            t => self.make_err(format!("expected an item or statement, found {}", t)),
         }
     }
     fn parse_let(&mut self) -> Result<Statement, ParserError> {
-        todo!()
+        let start = self.cur_span;
+        if self.cur_tok != Token::Let {
+            return self.make_err("expected let");
+        }
+        let mutable = self.advance() == Token::Mut;
+        if mutable {
+            self.advance();
+        }
+        let name = match &self.cur_tok {
+            Token::Ident(n) => n.clone(),
+            t => return self.make_err(format!("expected variable name, found {}", t)),
+        };
+        self.advance();
+
+        let rest = self.parse_let_rest();
+        // added after the value so `let x = x + 1;` reads the variable it shadows.
+        // a malformed `let` still declares it, so its uses aren't reported too
+        self.scopes.last_mut().unwrap().insert(name.clone());
+        let (tp, type_infered, value) = rest?;
+
+        Ok(Statement {
+            kind: StatementKind::Let(Let {
+                name,
+                mutable,
+                tp,
+                type_infered,
+                value,
+            }),
+            span: start.to(self.prev_span),
+        })
+    }
+    /// the type, value and `;` of a `let` after its name
+    fn parse_let_rest(&mut self) -> Result<(Type, bool, Option<Expression>), ParserError> {
+        let (tp, type_infered) = match self.cur_tok {
+            Token::Colin => (self.parse_type()?, false),
+            _ => (Type::new("_"), true),
+        };
+
+        let value = match self.cur_tok {
+            Token::Eq => {
+                self.advance();
+                Some(self.parse_expr()?)
+            }
+            Token::SemiColin => None,
+            _ => return self.make_err(format!("expected `=` or `;`, found {}", self.cur_tok)),
+        };
+
+        if self.cur_tok != Token::SemiColin {
+            return self.make_err(format!("expected `;`, found {}", self.cur_tok));
+        }
+        self.advance();
+        Ok((tp, type_infered, value))
+    }
+    /// the rest of an assignment after its target
+    fn parse_assign(&mut self, target: Expression) -> Result<Statement, ParserError> {
+        let mut place = &target;
+        while let ExpressionKind::Field(value, _) = &place.kind {
+            place = value;
+        }
+        if !matches!(place.kind, ExpressionKind::Value(Value::Var(_))) {
+            return Err(Diagnostic::error("invalid left-hand side of assignment", target.span)
+                .with_span_label("cannot assign to this expression"));
+        }
+        let op = match self.cur_tok {
+            Token::Eq => None,
+            Token::PlusEq => Some(Bop::Plus),
+            Token::MinusEq => Some(Bop::Minus),
+            Token::StarEq => Some(Bop::Star),
+            Token::SlashEq => Some(Bop::Slash),
+            _ => return self.make_err(format!("expected `=`, found {}", self.cur_tok)),
+        };
+        self.advance();
+        let value = self.parse_expr()?;
+
+        if self.cur_tok != Token::SemiColin {
+            return self.make_err(format!("expected `;`, found {}", self.cur_tok));
+        }
+        self.advance();
+
+        let span = target.span.to(self.prev_span);
+        Ok(Statement {
+            kind: StatementKind::Assign(Assign {
+                target,
+                op,
+                value,
+            }),
+            span,
+        })
     }
     fn parse_import(&mut self) -> Result<Statement, ParserError> {
+        let start = self.cur_span;
         if self.cur_tok != Token::Import {
             return self.make_err("expected import");
         }
@@ -111,13 +283,11 @@ impl Parser {
 
         // import all exported symboles
         let import = fs::read_to_string(&path)
-            .map_err(|e| ParserError {
-                msg: e.to_string(),
-                line: self.line,
-            });
+            .map_err(|e| Diagnostic::error(format!("could not import {:?}", path), self.prev_span)
+                .with_span_label(e));
         for (name, tp) in get_exports(import?) {
             match tp.as_str() {
-                "func" => self.funcs.insert(name),
+                "func" => self.funcs.insert(name, start.to(self.prev_span)),
                 _ => return self.make_err("unexpected type when parsing wat file")
             };
         }
@@ -127,45 +297,322 @@ impl Parser {
         }
         self.advance();
 
-        Ok(Statement::Import(Import {
-            path,
-        }))
+        Ok(Statement {
+            kind: StatementKind::Import(Import {
+                path,
+            }),
+            span: start.to(self.prev_span),
+        })
     }
     fn parse_struct_def(&mut self) -> Result<Statement, ParserError> {
+        let start = self.cur_span;
         if self.cur_tok != Token::Struct {
             return self.make_err("expected struct");
         }
+        let doc = self.take_doc();
         self.advance();
         let name = match &self.cur_tok {
             Token::Ident(i) => i.clone(),
             _ => return self.make_err("expected name")
         };
+        if self.types.contains(&name) {
+            return self.make_err(format!("the type `{}` is defined multiple times", name));
+        }
+        self.advance();
+        let fields = self.parse_fields_def()?;
+
+        self.types.insert(name.to_string());
+
+        Ok(Statement {
+            kind: StatementKind::Struct(Struct {
+                name: name.to_string(),
+                fields,
+                doc,
+            }),
+            span: start.to(self.prev_span),
+        })
+    }
+    /// the fields of a struct or enum variant, from the `{` to the `}`
+    fn parse_fields_def(&mut self) -> Result<Vec<Arg>, ParserError> {
+        if self.cur_tok != Token::LBrace {
+            return self.make_err(format!("expected `{{`, found {}", self.cur_tok));
+        }
+        self.advance();
+
+        let mut fields: Vec<Arg> = Vec::new();
+        while self.cur_tok != Token::RBrace && self.cur_tok.is_not_eof() {
+            let start = self.cur_span;
+            let doc = self.take_doc();
+            let field = match &self.cur_tok {
+                Token::Ident(f) => f.clone(),
+                t => return self.make_err(format!("expected field name, found {}", t)),
+            };
+            if let Some(prev) = fields.iter().find(|f| f.name == field) {
+                return Err(Diagnostic::error(format!("field `{}` is already declared", field), start)
+                    .with_label(prev.span, format!("`{}` first declared here", field)));
+            }
+            if self.advance() != Token::Colin {
+                return self.make_err(format!("expected `:`, found {}", self.cur_tok));
+            }
+            let tp = self.parse_type()?;
+            fields.push(Arg {
+                name: field,
+                tp,
+                span: start.to(self.prev_span),
+                doc,
+            });
+            match self.cur_tok {
+                Token::Coma => {
+                    self.advance();
+                }
+                Token::RBrace => {}
+                _ => return self.make_err(format!("expected `,` or `}}`, found {}", self.cur_tok)),
+            }
+        }
+        if self.cur_tok != Token::RBrace {
+            return self.make_err(format!("expected `}}`, found {}", self.cur_tok));
+        }
+        self.advance();
+        Ok(fields)
+    }
+    fn parse_enum_def(&mut self) -> Result<Statement, ParserError> {
+        let start = self.cur_span;
+        let doc = self.take_doc();
+        let name = match self.advance() {
+            Token::Ident(i) => i,
+            t => return self.make_err(format!("expected name, found {}", t)),
+        };
+        if self.types.contains(&name) {
+            return self.make_err(format!("the type `{}` is defined multiple times", name));
+        }
+        if self.advance() != Token::LBrace {
+            return self.make_err(format!("expected `{{`, found {}", self.cur_tok));
+        }
         self.advance();
-        // todo: make this actualy do something
-        while self.cur_tok != Token::RBrace {
+
+        let mut variants: Vec<Variant> = Vec::new();
+        while self.cur_tok != Token::RBrace && self.cur_tok.is_not_eof() {
+            let span = self.cur_span;
+            let variant_doc = self.take_doc();
+            let variant = match &self.cur_tok {
+                Token::Ident(v) => v.clone(),
+                t => return self.make_err(format!("expected variant name, found {}", t)),
+            };
+            if let Some(prev) = variants.iter().find(|v| v.name == variant) {
+                return Err(Diagnostic::error(format!("variant `{}` is already declared", variant), span)
+                    .with_label(prev.span, format!("`{}` first declared here", variant)));
+            }
             self.advance();
+            let (shape, fields) = match self.cur_tok {
+                Token::LPeren => {
+                    let mut fields = Vec::new();
+                    while self.cur_tok != Token::RPeren && self.cur_tok.is_not_eof() {
+                        // skips the `(` or `,` before the type
+                        let start = self.peek_span;
+                        let tp = self.parse_type()?;
+                        fields.push(Arg {
+                            name: fields.len().to_string(),
+                            tp,
+                            span: start.to(self.prev_span),
+                            doc: None,
+                        });
+                        if !matches!(self.cur_tok, Token::Coma | Token::RPeren) {
+                            return self.make_err(format!("expected `,` or `)`, found {}", self.cur_tok));
+                        }
+                    }
+                    self.advance();
+                    (VariantShape::Tuple, fields)
+                }
+                Token::LBrace => (VariantShape::Named, self.parse_fields_def()?),
+                _ => (VariantShape::Unit, Vec::new()),
+            };
+            variants.push(Variant {
+                name: variant,
+                span,
+                shape,
+                fields,
+                doc: variant_doc,
+            });
+            match self.cur_tok {
+                Token::Coma => {
+                    self.advance();
+                }
+                Token::RBrace => {}
+                _ => return self.make_err(format!("expected `,` or `}}`, found {}", self.cur_tok)),
+            }
+        }
+        if self.cur_tok != Token::RBrace {
+            return self.make_err(format!("expected `}}`, found {}", self.cur_tok));
         }
         self.advance();
 
-        self.types.insert(name.to_string());
+        self.types.insert(name.clone());
 
-        Ok(Statement::Struct(Struct {
-            name: name.to_string(),
-            fields: Vec::new(),
-        }))
+        Ok(Statement {
+            kind: StatementKind::Enum(Enum {
+                name,
+                variants,
+                doc,
+            }),
+            span: start.to(self.prev_span),
+        })
+    }
+    /// an expression followed by a block, like the condition of an `if`
+    fn parse_cond(&mut self) -> Result<Expression, ParserError> {
+        let prev = std::mem::replace(&mut self.no_struct_lit, true);
+        let cond = self.parse_expr();
+        self.no_struct_lit = prev;
+        cond
+    }
+    fn parse_match(&mut self) -> Result<Expression, ParserError> {
+        let start = self.cur_span;
+        self.advance();
+        let value = self.parse_cond()?;
+        if self.cur_tok != Token::LBrace {
+            return self.make_err(format!("expected `{{`, found {}", self.cur_tok));
+        }
+        self.advance();
+
+        let mut arms = Vec::new();
+        while self.cur_tok != Token::RBrace && self.cur_tok.is_not_eof() {
+            let pattern = self.parse_pattern()?;
+            if self.cur_tok != Token::FatArrow {
+                return self.make_err(format!("expected `=>`, found {}", self.cur_tok));
+            }
+            self.advance();
+
+            // the names the pattern binds are only in scope in its arm
+            self.scopes.push(pattern.bindings().into_iter().collect());
+            let is_block = self.cur_tok == Token::LBrace;
+            let body = if is_block {
+                self.parse_block()
+            } else {
+                self.parse_expr().map(|e| vec![Ast::Expression(e)])
+            };
+            self.scopes.pop();
+            arms.push(MatchArm {
+                pattern,
+                body: body?,
+            });
+            // arms that are blocks don't need a comma after them
+            match self.cur_tok {
+                Token::Coma => {
+                    self.advance();
+                }
+                Token::RBrace => {}
+                _ if is_block => {}
+                _ => return self.make_err(format!("expected `,` or `}}`, found {}", self.cur_tok)),
+            }
+        }
+        if self.cur_tok != Token::RBrace {
+            return self.make_err(format!("expected `}}`, found {}", self.cur_tok));
+        }
+        self.advance();
+
+        Ok(Expression {
+            kind: ExpressionKind::Match(Match {
+                value: Box::new(value),
+                arms,
+            }),
+            span: start.to(self.prev_span),
+            tp: None,
+        })
+    }
+    fn parse_pattern(&mut self) -> Result<Pattern, ParserError> {
+        let start = self.cur_span;
+        let name = match &self.cur_tok {
+            Token::Ident(n) => n.clone(),
+            t => return self.make_err(format!("expected a pattern, found {}", t)),
+        };
+        self.advance();
+        if name == "_" {
+            return Ok(Pattern {
+                kind: PatternKind::Wildcard,
+                span: start,
+            });
+        }
+        if self.cur_tok != Token::ColinColin {
+            return Ok(Pattern {
+                kind: PatternKind::Binding(name),
+                span: start,
+            });
+        }
+        let variant = match self.advance() {
+            Token::Ident(v) => v,
+            t => return self.make_err(format!("expected variant name, found {}", t)),
+        };
+        self.advance();
+
+        let mut fields = Vec::new();
+        let mut rest = false;
+        let (shape, close) = match self.cur_tok {
+            Token::LPeren => (VariantShape::Tuple, Token::RPeren),
+            Token::LBrace => (VariantShape::Named, Token::RBrace),
+            _ => (VariantShape::Unit, Token::Eof),
+        };
+        if shape != VariantShape::Unit {
+            self.advance();
+            while self.cur_tok != close && self.cur_tok.is_not_eof() {
+                if rest {
+                    return self.make_err("`..` must be at the end of the pattern");
+                }
+                if self.cur_tok == Token::DotDot {
+                    self.advance();
+                    rest = true;
+                } else if shape == VariantShape::Tuple {
+                    fields.push((fields.len().to_string(), self.parse_pattern()?));
+                } else {
+                    let field_span = self.cur_span;
+                    let field = match &self.cur_tok {
+                        Token::Ident(f) => f.clone(),
+                        t => return self.make_err(format!("expected field name, found {}", t)),
+                    };
+                    // `field` is short for `field: field`
+                    let pattern = if self.advance() == Token::Colin {
+                        self.advance();
+                        self.parse_pattern()?
+                    } else {
+                        Pattern {
+                            kind: PatternKind::Binding(field.clone()),
+                            span: field_span,
+                        }
+                    };
+                    fields.push((field, pattern));
+                }
+                if self.cur_tok == Token::Coma {
+                    self.advance();
+                } else if self.cur_tok != close {
+                    return self.make_err(format!("expected `,` or {}, found {}", close, self.cur_tok));
+                }
+            }
+            if self.cur_tok != close {
+                return self.make_err(format!("expected {}, found {}", close, self.cur_tok));
+            }
+            self.advance();
+        }
+
+        Ok(Pattern {
+            kind: PatternKind::Variant {
+                name,
+                variant,
+                shape,
+                fields,
+                rest,
+            },
+            span: start.to(self.prev_span),
+        })
     }
     fn parse_if(&mut self) -> Result<Expression, ParserError> {
+        let start = self.cur_span;
         if self.cur_tok != Token::If {
             return self.make_err("expected if");
         }
         self.advance();
-        let condition = self.parse_expr()?;
-        println!("after cond {:?}", self.cur_tok);
+        let condition = self.parse_cond()?;
 
         let block = self.parse_block()?;
 
         let elsifs = self.parse_els_ifs()?;
-        println!("elsifs: {:?}", elsifs);
 
         let els = if self.cur_tok == Token::Else {
             self.advance();
@@ -174,15 +621,120 @@ impl Parser {
             None
         };
 
-        Ok(Expression::If(If {
+        Ok(Expression {
+            kind: ExpressionKind::If(If {
+                condition: Box::new(condition),
+                block,
+                elsifs,
+                els,
+            }),
+            span: start.to(self.prev_span),
+            tp: None,
+        })
+    }
+    /// `{ ... }` used as a value
+    fn parse_block_expr(&mut self) -> Result<Expression, ParserError> {
+        let start = self.cur_span;
+        // a block in a condition can hold struct literals, like a parenthesized expression
+        let prev = std::mem::replace(&mut self.no_struct_lit, false);
+        let block = self.parse_block();
+        self.no_struct_lit = prev;
+        Ok(Expression {
+            kind: ExpressionKind::Block(block?),
+            span: start.to(self.prev_span),
+            tp: None,
+        })
+    }
+    /// a while or for loop, optionally labeled `'label: while ...`
+    fn parse_loop(&mut self) -> Result<Expression, ParserError> {
+        let start = self.cur_span;
+        let label = match &self.cur_tok {
+            Token::Label(l) => {
+                let l = l.clone();
+                if self.advance() != Token::Colin {
+                    return self.make_err(format!("expected `:` after the label, found {}", self.cur_tok));
+                }
+                self.advance();
+                Some(l)
+            }
+            _ => None,
+        };
+        let kind = match self.cur_tok {
+            Token::While => self.parse_while(label)?,
+            Token::For => self.parse_for(label)?,
+            _ => return self.make_err(format!("expected a loop after the label, found {}", self.cur_tok)),
+        };
+        Ok(Expression {
+            kind,
+            span: start.to(self.prev_span),
+            tp: None,
+        })
+    }
+    fn parse_while(&mut self, label: Option<String>) -> Result<ExpressionKind, ParserError> {
+        self.advance();
+        let condition = self.parse_cond()?;
+        let block = self.parse_block()?;
+
+        Ok(ExpressionKind::While(While {
+            label,
             condition: Box::new(condition),
             block,
-            elsifs,
-            els,
         }))
     }
+    fn parse_for(&mut self, label: Option<String>) -> Result<ExpressionKind, ParserError> {
+        let var = match self.advance() {
+            Token::Ident(n) => n,
+            t => return self.make_err(format!("expected loop variable, found {}", t)),
+        };
+        let var_span = self.cur_span;
+        if self.advance() != Token::In {
+            return self.make_err(format!("expected `in`, found {}", self.cur_tok));
+        }
+        self.advance();
+        let iter = self.parse_cond()?;
+
+        // the loop variable is only in scope inside the body
+        self.scopes.push(HashSet::from([var.clone()]));
+        let block = self.parse_block();
+        self.scopes.pop();
+
+        Ok(ExpressionKind::For(For {
+            label,
+            var,
+            var_span,
+            iter: Box::new(iter),
+            block: block?,
+        }))
+    }
+    /// `break` or `continue`, optionally followed by a label
+    fn parse_break(&mut self) -> Result<Statement, ParserError> {
+        let start = self.cur_span;
+        let is_break = self.cur_tok == Token::Break;
+        let label = match self.advance() {
+            Token::Label(l) => {
+                self.advance();
+                Some(l)
+            }
+            _ => None,
+        };
+        match self.cur_tok {
+            Token::SemiColin => {
+                self.advance();
+            }
+            Token::RBrace => {}
+            _ => return self.make_err(format!("expected `;`, found {}", self.cur_tok)),
+        }
+        let kind = if is_break {
+            StatementKind::Break(label)
+        } else {
+            StatementKind::Continue(label)
+        };
+        Ok(Statement {
+            kind,
+            span: start.to(self.prev_span),
+        })
+    }
     fn parse_els_ifs(&mut self) -> Result<Option<Vec<ElseIf>>, ParserError> {
-        println!("elsif cur_tok: {:?}, peek: {:?}", self.cur_tok, self.peek_tok);
         if self.cur_tok != Token::Else || self.peek_tok != Token::If {
             return Ok(None);
         }
@@ -190,7 +742,7 @@ impl Parser {
         while self.cur_tok == Token::Else && self.peek_tok == Token::If && self.cur_tok.is_not_eof() {
             self.advance();
             self.advance();
-            let condition = self.parse_expr()?;
+            let condition = self.parse_cond()?;
             let block = self.parse_block()?;
             elsifs.push(ElseIf {
                 condition: Box::new(condition),
@@ -199,53 +751,316 @@ impl Parser {
         }
         Ok(Some(elsifs))
     }
-    fn parse_expr(&mut self) -> Result<Expression, ParserError> {
-        let lhs = self.parse_value()?;
+    /// an expression used as a statement. it has to be followed by a semicolon
+    /// unless it is the last thing in its block, where it is the value of the block
+    fn parse_expr_statement(&mut self) -> Result<Ast, ParserError> {
+        let expr = self.parse_expr()?;
+        match self.cur_tok {
+            Token::Eq | Token::PlusEq | Token::MinusEq | Token::StarEq | Token::SlashEq => {
+                Ok(Ast::Statement(self.parse_assign(expr)?))
+            }
+            Token::SemiColin => {
+                self.advance();
+                let span = expr.span.to(self.prev_span);
+                Ok(Ast::Statement(Statement {
+                    kind: StatementKind::Expression(expr),
+                    span,
+                }))
+            }
+            Token::RBrace => Ok(Ast::Expression(expr)),
+            _ => self.make_err(format!("expected `;`, found {}", self.cur_tok)),
+        }
+    }
+    fn parse_return(&mut self) -> Result<Statement, ParserError> {
+        let start = self.cur_span;
         self.advance();
-        if ! self.cur_tok.is_operator() {
-            return Ok(lhs);
+        let value = match self.cur_tok {
+            Token::SemiColin | Token::RBrace => None,
+            _ => Some(self.parse_expr()?),
+        };
+        match self.cur_tok {
+            Token::SemiColin => {
+                self.advance();
+            }
+            Token::RBrace => {}
+            _ => return self.make_err(format!("expected `;`, found {}", self.cur_tok)),
         }
-        let op = self.cur_tok.clone();
+        Ok(Statement {
+            kind: StatementKind::Return(value),
+            span: start.to(self.prev_span),
+        })
+    }
+    fn parse_expr(&mut self) -> Result<Expression, ParserError> {
+        let start = self.parse_binary(0)?;
+        // ranges bind looser than every binary operator
+        let inclusive = match self.cur_tok {
+            Token::DotDot => false,
+            Token::DotDotEq => true,
+            _ => return Ok(start),
+        };
         self.advance();
+        let end = self.parse_binary(0)?;
+        let span = start.span.to(end.span);
+        Ok(Expression {
+            kind: ExpressionKind::Range(Box::new(start), Box::new(end), inclusive),
+            span,
+            tp: None,
+        })
+    }
+    /// precedence climbing. parses a value followed by every binary operator
+    /// that binds at least as tightly as `min_prec`
+    fn parse_binary(&mut self, min_prec: u8) -> Result<Expression, ParserError> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            if self.cur_tok == Token::As && ExpressionKind::CAST_PRECEDENCE >= min_prec {
+                let tp = self.parse_type()?;
+                let span = lhs.span.to(self.prev_span);
+                lhs = Expression {
+                    kind: ExpressionKind::Cast(Box::new(lhs), tp),
+                    span,
+                    tp: None,
+                };
+                continue;
+            }
+            let Some(op) = Bop::from_token(self.cur_tok.clone()) else {
+                break;
+            };
+            let prec = op.precedence();
+            if prec < min_prec {
+                break;
+            }
+            let op_span = self.cur_span;
+            self.advance();
+            // left associative operators only let tighter operators into their rhs
+            let next_min = if op.is_right_assoc() { prec } else { prec + 1 };
+            let rhs = self.parse_binary(next_min)?;
 
-        let rhs = self.parse_value()?;
+            if op.is_comparison() && Bop::from_token(self.cur_tok.clone()).is_some_and(|b| b.is_comparison()) {
+                return Err(Diagnostic::error("comparison operators cannot be chained", self.cur_span)
+                    .with_label(op_span, "first comparison here")
+                    .with_help("split the comparison up, or use parentheses"));
+            }
 
+            let span = lhs.span.to(rhs.span);
+            lhs = Expression {
+                kind: ExpressionKind::BinaryOperation(Box::new(lhs), op, Box::new(rhs)),
+                span,
+                tp: None,
+            };
+        }
+        Ok(lhs)
+    }
+    fn parse_unary(&mut self) -> Result<Expression, ParserError> {
+        let Some(op) = Uop::from_token(&self.cur_tok) else {
+            return self.parse_postfix();
+        };
+        let start = self.cur_span;
         self.advance();
+        let expr = self.parse_binary(Uop::PRECEDENCE + 1)?;
+        let span = start.to(expr.span);
 
-        let op = match Bop::from_token(op) {
-            Some(v) => v,
-            None => return self.make_err("expected binary operator"),
+        // fold negative literals so `-5` is just a value
+        let kind = match (op, expr.kind) {
+            (Uop::Neg, ExpressionKind::Value(Value::Int(i, suffix))) => ExpressionKind::Value(Value::Int(-i, suffix)),
+            (Uop::Neg, ExpressionKind::Value(Value::Float(f, suffix))) => ExpressionKind::Value(Value::Float(-f, suffix)),
+            (op, kind) => ExpressionKind::UnaryOperation(op, Box::new(Expression {
+                kind,
+                span: expr.span,
+                tp: None,
+            })),
         };
-
-        Ok(Expression::BinaryOperation(Box::new(lhs), op, Box::new(rhs)))
+        Ok(Expression {
+            kind,
+            span,
+            tp: None,
+        })
     }
+    /// a value followed by any number of field accesses and indexes
+    fn parse_postfix(&mut self) -> Result<Expression, ParserError> {
+        let mut expr = self.parse_value()?;
+        loop {
+            expr = match self.cur_tok {
+                Token::Dot => {
+                    let field = match self.advance() {
+                        Token::Ident(f) => f,
+                        t => return self.make_err(format!("expected field name, found {}", t)),
+                    };
+                    self.advance();
+                    Expression {
+                        span: expr.span.to(self.prev_span),
+                        kind: ExpressionKind::Field(Box::new(expr), field),
+                        tp: None,
+                    }
+                }
+                Token::LBracket => {
+                    let open = self.cur_span;
+                    self.advance();
+                    let prev = std::mem::replace(&mut self.no_struct_lit, false);
+                    let index = self.parse_expr();
+                    self.no_struct_lit = prev;
+                    let index = index?;
+                    if self.cur_tok != Token::RBracket {
+                        return Err(Diagnostic::error(format!("expected `]`, found {}", self.cur_tok), self.cur_span)
+                            .with_label(open, "unclosed bracket"));
+                    }
+                    self.advance();
+                    Expression {
+                        span: expr.span.to(self.prev_span),
+                        kind: ExpressionKind::Index(Box::new(expr), Box::new(index)),
+                        tp: None,
+                    }
+                }
+                _ => return Ok(expr),
+            };
+        }
+    }
+    /// parses a single value and advances past it
     fn parse_value(&mut self) -> Result<Expression, ParserError> {
-        match &self.cur_tok {
+        let start = self.cur_span;
+        let value = match &self.cur_tok {
+            Token::LPeren => {
+                self.advance();
+                let prev = std::mem::replace(&mut self.no_struct_lit, false);
+                let expr = self.parse_expr();
+                self.no_struct_lit = prev;
+                let mut expr = expr?;
+                if self.cur_tok != Token::RPeren {
+                    return Err(Diagnostic::error(format!("expected `)`, found {}", self.cur_tok), self.cur_span)
+                        .with_label(start, "unclosed parenthesis"));
+                }
+                self.advance();
+                expr.span = start.to(self.prev_span);
+                return Ok(expr);
+            }
+            Token::If => return self.parse_if(),
+            Token::Match => return self.parse_match(),
+            Token::LBrace => return self.parse_block_expr(),
             Token::Ident(i) => {
                 if self.get_var(i) {
-                    Ok(Expression::Value(Value::Var(i.clone())))
-                } else if self.funcs.get(i).is_some() && self.peek_tok == Token::LPeren {
-                    Ok(Expression::Value(Value::FnCall(self.parse_fn_call()?)))
+                    Value::Var(i.clone())
+                } else if let Some(def) = self.funcs.get(i) {
+                    if self.peek_tok != Token::LPeren {
+                        return Err(Diagnostic::error(format!("expected a value, found function `{}`", i), start)
+                            .with_label(*def, format!("function `{}` defined here", i))
+                            .with_help(format!("call it with `{}()`", i)));
+                    }
+                    let call = self.parse_fn_call()?;
+                    return Ok(Expression {
+                        kind: ExpressionKind::Value(Value::FnCall(call)),
+                        span: start.to(self.prev_span),
+                        tp: None,
+                    });
+                } else if self.types.contains(i) && self.peek_tok == Token::ColinColin {
+                    let lit = self.parse_enum_lit()?;
+                    return Ok(Expression {
+                        kind: ExpressionKind::Value(Value::EnumLit(lit)),
+                        span: start.to(self.prev_span),
+                        tp: None,
+                    });
+                } else if self.types.contains(i) && self.peek_tok == Token::LBrace && !self.no_struct_lit {
+                    let lit = self.parse_struct_lit()?;
+                    return Ok(Expression {
+                        kind: ExpressionKind::Value(Value::StructLit(lit)),
+                        span: start.to(self.prev_span),
+                        tp: None,
+                    });
                 } else {
-                    println!("{:?}", self.funcs);
-                    self.make_err(format!("unknown identifyer {:?}", i))
+                    return Err(Diagnostic::error(format!("unknown identifyer {:?}", i), start)
+                        .with_span_label("not found in this scope"))
                 }
             }
-            Token::Number(n) => Ok(match n {
-                Number::F32(f) => Expression::Value(Value::F32(*f)),
-                Number::I32(i) => Expression::Value(Value::I32(*i)),
-            }),
+            Token::True => Value::Bool(true),
+            Token::Char(c) => Value::Char(*c),
+            Token::False => Value::Bool(false),
+            Token::Number(n) => match n {
+                Number::Float(f, suffix) => Value::Float(*f, suffix.as_ref().map(Type::new)),
+                Number::Int(i, suffix) => Value::Int(*i as i128, suffix.as_ref().map(Type::new)),
+            },
             Token::String(s) => {
                 let mut values = HashMap::new();
-                let chars = s.chars().map(|x| Constant::Value(Value::I32(x as i32))).collect::<Vec<_>>();
+                // strings are stored as their utf-8 bytes
+                let chars = s.bytes().map(|x| Constant::Value(Value::Int(x as i128, None))).collect::<Vec<_>>();
                 values.insert(String::from("String"), Constant::Arr(chars));
-                Ok(Expression::Value(Value::TypeConstr(TypeConstr {
+                Value::TypeConstr(TypeConstr {
                     name: "String".to_string(),
                     values,
-                })))
+                })
             }
-            t => unreachable!("{:?} on line: {}", t, self.line)
+            t => return self.make_err(format!("expected a value, found {}", t)),
+        };
+        self.advance();
+        Ok(Expression {
+            kind: ExpressionKind::Value(value),
+            span: start,
+            tp: None,
+        })
+    }
+    fn parse_struct_lit(&mut self) -> Result<StructLit, ParserError> {
+        let name = match &self.cur_tok {
+            Token::Ident(n) => n.clone(),
+            _ => return self.make_err("expected struct name"),
+        };
+        self.advance();
+        Ok(StructLit {
+            name,
+            fields: self.parse_field_values()?,
+        })
+    }
+    fn parse_enum_lit(&mut self) -> Result<EnumLit, ParserError> {
+        let name = match &self.cur_tok {
+            Token::Ident(n) => n.clone(),
+            _ => return self.make_err("expected enum name"),
+        };
+        self.advance();
+        let variant = match self.advance() {
+            Token::Ident(v) => v,
+            t => return self.make_err(format!("expected variant name, found {}", t)),
+        };
+        self.advance();
+        let (shape, fields) = match self.cur_tok {
+            Token::LPeren => {
+                let args = self.parse_args()?;
+                let fields = args.into_iter().enumerate().map(|(i, a)| (i.to_string(), a.span, a)).collect();
+                (VariantShape::Tuple, fields)
+            }
+            Token::LBrace if !self.no_struct_lit => (VariantShape::Named, self.parse_field_values()?),
+            _ => (VariantShape::Unit, Vec::new()),
+        };
+        Ok(EnumLit {
+            name,
+            variant,
+            shape,
+            fields,
+        })
+    }
+    /// `{ field: value, ... }` of a struct or enum literal
+    fn parse_field_values(&mut self) -> Result<Vec<(String, Span, Expression)>, ParserError> {
+        self.advance();
+        let mut fields = Vec::new();
+        while self.cur_tok != Token::RBrace && self.cur_tok.is_not_eof() {
+            let field = match &self.cur_tok {
+                Token::Ident(f) => f.clone(),
+                t => return self.make_err(format!("expected field name, found {}", t)),
+            };
+            let field_span = self.cur_span;
+            if self.advance() != Token::Colin {
+                return self.make_err(format!("expected `:`, found {}", self.cur_tok));
+            }
+            self.advance();
+            fields.push((field, field_span, self.parse_expr()?));
+            match self.cur_tok {
+                Token::Coma => {
+                    self.advance();
+                }
+                Token::RBrace => {}
+                _ => return self.make_err(format!("expected `,` or `}}`, found {}", self.cur_tok)),
+            }
+        }
+        if self.cur_tok != Token::RBrace {
+            return self.make_err(format!("expected `}}`, found {}", self.cur_tok));
         }
+        self.advance();
+        Ok(fields)
     }
     fn parse_fn_call(&mut self) -> Result<FnCall, ParserError> {
         let name = match &self.cur_tok {
@@ -267,22 +1082,60 @@ impl Parser {
         let mut args = Vec::new();
         while self.cur_tok != Token::RPeren && self.cur_tok.is_not_eof(){
             args.push(self.parse_expr()?);
+            match self.cur_tok {
+                Token::Coma => {
+                    self.advance();
+                }
+                Token::RPeren => {}
+                _ => return self.make_err(format!("expected `,` or `)`, found {}", self.cur_tok)),
+            }
         }
         if self.cur_tok != Token::RPeren {
             return self.make_err("expected clonsing parenthesis")
         }
         self.advance();
-        return Ok(args);
+        Ok(args)
     }
     fn parse_fn_def(&mut self) -> Result<Statement, ParserError> {
-        assert!(self.cur_tok == Token::Fn);
-
+        if self.cur_tok != Token::Fn {
+            return self.make_err("expected fn");
+        }
+        let start = self.cur_span;
+        let doc = self.take_doc();
 
         let name = match self.advance() {
             Token::Ident(n) => n,
-            _ => unreachable!(),
+            t => return self.make_err(format!("expected function name, found {}", t)),
         };
+        let name_span = self.cur_span;
+        if let Some(prev) = self.funcs.get(&name) {
+            return Err(Diagnostic::error(format!("function `{}` is defined more than once", name), name_span)
+                .with_label(*prev, format!("previous definition of `{}` here", name)));
+        }
         self.advance();
+        // the params are in a scope of their own around the body
+        self.scopes.push(HashSet::new());
+        let rest = self.parse_fn_rest();
+        self.scopes.pop();
+        let (args, ret_tp, ret_span, body) = rest?;
+
+        self.funcs.insert(name.clone(), name_span);
+
+        
+        Ok(Statement {
+            kind: StatementKind::Function(Function {
+                name,
+                args,
+                ret_tp,
+                ret_span,
+                body,
+                doc,
+            }),
+            span: start.to(self.prev_span),
+        })
+    }
+    /// parses the params, return type and body of a function
+    fn parse_fn_rest(&mut self) -> Result<(Vec<Arg>, Type, Span, Vec<Ast>), ParserError> {
         let args = self.parse_args_def()?;
 
         let ret_tp = match self.cur_tok {
@@ -291,18 +1144,10 @@ impl Parser {
             }
             _ => Type { name: "()".to_string() },
         };
+        let ret_span = self.prev_span;
 
         let body = self.parse_block()?;
-
-        self.funcs.insert(name.clone());
-
-        
-        return Ok(Statement::Function(Function {
-            name,
-            args,
-            ret_tp,
-            body,
-        }));
+        Ok((args, ret_tp, ret_span, body))
     }
     fn parse_args_def(&mut self) -> Result<Vec<Arg>, ParserError> {
         if self.cur_tok != Token::LPeren {
@@ -313,22 +1158,26 @@ impl Parser {
         while self.cur_tok != Token::RPeren && !self.cur_tok.is_eof() {
             args.push(self.parse_arg_def()?);
         }
+        if self.cur_tok != Token::RPeren {
+            return self.make_err("expected closing parenthesis")
+        }
         self.advance();
-        return Ok(args);
+        Ok(args)
     }
     fn parse_arg_def(&mut self) -> Result<Arg, ParserError> {
+        let start = self.cur_span;
         let name = match &self.cur_tok {
             Token::Ident(name) => name.clone(),
-            t => unreachable!("{:?}", t),
+            t => return self.make_err(format!("expected argument name, found {}", t)),
         };
         if self.advance() != Token::Colin {
             return self.make_err("expected type")
         }
         let tp = self.parse_type()?;
+        let span = start.to(self.prev_span);
 
         if self.cur_tok != Token::Coma {
             if self.cur_tok != Token::RPeren {
-                println!("{:?}", self.cur_tok);
                 return self.make_err("expected coma")
             }
         } else {
@@ -339,16 +1188,18 @@ impl Parser {
         }
         self.scopes.last_mut().unwrap().insert(name.clone());
 
-        return Ok(Arg {
+        Ok(Arg {
             name,
-            tp
+            tp,
+            span,
+            doc: None,
         })
     }
     fn parse_type(&mut self) -> Result<Type, ParserError> {
         self.advance();
         let tp = match &self.cur_tok {
             Token::Ident(i) => {
-                if self.types.get(i).is_some() {
+                if self.types.contains(i) {
                     Type { name: i.clone() }
                 } else {
                     return  self.make_err("type not defined");
@@ -357,30 +1208,226 @@ impl Parser {
             _ => return self.make_err("expected type")
         };
         self.advance();
-        return Ok(tp);
+        Ok(tp)
     }
     fn advance(&mut self) -> Token {
+        self.prev_span = self.cur_span;
+        self.warn_unused_docs();
+        self.docs = std::mem::take(&mut self.peek_docs);
         if self.peek_tok.is_eof() {
             self.cur_tok = Token::Eof;
+            self.cur_span = self.peek_span;
             return Token::Eof;
         }
         self.cur_tok = self.peek_tok.clone();
+        self.cur_span = self.peek_span;
         self.idx += 1;
-        while self.tokens.get(self.idx).unwrap() == &Token::NewLine {
-            self.line += 1;
+        self.skip_newlines();
+        self.cur_tok.clone()
+    }
+    /// moves `idx` past any newlines and doc comments and loads the token it
+    /// lands on into `peek_tok`. the doc comments are kept in `peek_docs`
+    fn skip_newlines(&mut self) {
+        while let Some(t) = self.tokens.get(self.idx) {
+            match &t.token {
+                Token::NewLine => {}
+                Token::DocComment(doc) => self.peek_docs.push((doc.clone(), t.span)),
+                _ => break,
+            }
             self.idx += 1;
         }
-        self.peek_tok = self.tokens.get(self.idx).unwrap().clone();
-        self.cur_tok.clone()
+        match self.tokens.get(self.idx) {
+            Some(t) => {
+                self.peek_tok = t.token.clone();
+                self.peek_span = t.span;
+            }
+            None => self.peek_tok = Token::Eof,
+        }
+    }
+    /// the doc comments before the item at `cur_tok`, one line each
+    fn take_doc(&mut self) -> Option<String> {
+        let docs = std::mem::take(&mut self.docs);
+        (!docs.is_empty()).then(|| docs.into_iter().map(|(d, _)| d).collect::<Vec<_>>().join("\n"))
+    }
+    /// doc comments that weren't taken by the item after them
+    fn warn_unused_docs(&mut self) {
+        let Some((_, first)) = self.docs.first() else {
+            return;
+        };
+        let span = first.to(self.docs.last().unwrap().1);
+        self.docs.clear();
+        self.diagnostics.push(Diagnostic::warning("unused doc comment", span)
+            .with_span_label("doc comments only document functions, structs, enums and their fields and variants")
+            .with_help("use `//` for a normal comment"));
     }
     fn get_var(&self, name: &String) -> bool {
         self.scopes.iter().rev().find(|x| x.get(name).is_some() ).is_some()
     }
     fn make_err<T: ToString, U>(&self, msg: T) -> Result<U, ParserError> {
-        Err(ParserError {
-            msg: msg.to_string(),
-            line: self.line,
-        })
+        Err(Diagnostic::error(msg, self.cur_span))
   
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::{ast::{Ast, Statement, StatementKind}, lexer::lex, token::Token};
+
+    /// the program and the error messages from parsing `src`
+    fn parse_src(src: &str) -> (Vec<Ast>, Vec<String>) {
+        let (tokens, _) = lex(src);
+        let tokens = tokens.into_iter().filter(|t| !t.token.is_trivia() && t.token != Token::Elegal).collect();
+        let (ast, diagnostics) = parse(tokens);
+        (ast, diagnostics.into_iter().filter(|d| d.is_error()).map(|d| d.msg).collect())
+    }
+
+    /// the tail expression of a function returning `src`, with its grouping shown by parentheses
+    fn expr(src: &str) -> String {
+        let (ast, errors) = parse_src(&format!("fn f(a: i32): i32 {{ {} }}", src));
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Some(Ast::Statement(Statement { kind: StatementKind::Function(f), .. })) = ast.first() else {
+            panic!("expected a function");
+        };
+        let Some(Ast::Expression(e)) = f.body.last() else {
+            panic!("expected a tail expression");
+        };
+        e.to_string().replace("Int(", "").replace(", None)", "")
+    }
+
+    #[test]
+    fn binary_operators_group_by_precedence() {
+        assert_eq!(expr("1 + 2 * 3 - 4"), "((1 Plus (2 Star 3)) Minus 4)");
+        // `^` is right associative, so this is 2^9 = 512
+        assert_eq!(expr("2 ^ 3 ^ 2"), "(2 Carret (3 Carret 2))");
+        // and binds tighter than unary minus, so this is -(2^2) = -4
+        assert_eq!(expr("-2 ^ 2"), "(Neg (2 Carret 2))");
+        assert_eq!(expr("a < 1 || a > 2 && true"), "((Var(\"a\") Lt 1) Or ((Var(\"a\") Gt 2) And Bool(true)))");
+    }
+
+    #[test]
+    fn comparisons_cannot_be_chained() {
+        let (_, errors) = parse_src("fn f(a: i32): bool { 1 < a < 3 }");
+        assert_eq!(errors, ["comparison operators cannot be chained"]);
+        let (_, errors) = parse_src("fn f(a: i32): bool { a == 1 != true }");
+        assert_eq!(errors, ["comparison operators cannot be chained"]);
+    }
+
+    #[test]
+    fn blocks_are_values() {
+        assert_eq!(expr("1 + { a } * 2"), "(1 Plus ({\nVar(\"a\")\n} Star 2))");
+        let (ast, errors) = parse_src("fn f() { let b = { 0 }; { let c = b; } if { b == 0 } {} }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Some(Ast::Statement(Statement { kind: StatementKind::Function(f), .. })) = ast.first() else {
+            panic!("expected a function");
+        };
+        assert_eq!(f.body.len(), 3);
+        // the variables declared in a block are only in scope inside it
+        let (_, errors) = parse_src("fn f(): i32 { { let c = 1; } c }");
+        assert_eq!(errors, ["unknown identifyer \"c\""]);
+    }
+
+    #[test]
+    fn doc_comments_attach_to_the_item_after_them() {
+        let (ast, errors) = parse_src("
+/// adds
+/// two numbers
+fn add(a: i32, b: i32): i32 { a + b }
+// not a doc comment
+fn sub(a: i32, b: i32): i32 { a - b }
+/// a point
+struct P {
+    /// across
+    x: i32,
+    y: i32,
+}
+/// a shape
+enum Shape {
+    /// round
+    Circle(f32),
+    Rect {
+        /// wide
+        w: i32,
+    },
+}
+");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let docs = ast.iter().map(|section| match section {
+            Ast::Statement(Statement { kind: StatementKind::Function(f), .. }) => vec![f.doc.clone()],
+            Ast::Statement(Statement { kind: StatementKind::Struct(s), .. }) => {
+                [s.doc.clone()].into_iter().chain(s.fields.iter().map(|f| f.doc.clone())).collect()
+            }
+            Ast::Statement(Statement { kind: StatementKind::Enum(e), .. }) => {
+                let fields = e.variants.iter().flat_map(|v| [v.doc.clone()].into_iter().chain(v.fields.iter().map(|f| f.doc.clone())));
+                [e.doc.clone()].into_iter().chain(fields).collect()
+            }
+            _ => panic!("expected an item"),
+        }).collect::<Vec<_>>();
+        let doc = |d: &str| Some(d.to_string());
+        assert_eq!(docs, [
+            vec![doc("adds\ntwo numbers")],
+            vec![None],
+            vec![doc("a point"), doc("across"), None],
+            vec![doc("a shape"), doc("round"), None, None, doc("wide")],
+        ]);
+    }
+
+    #[test]
+    fn doc_comments_on_anything_else_are_unused() {
+        let (tokens, _) = lex("fn f() {\n    /// nothing\n    let x = 1;\n}");
+        let tokens = tokens.into_iter().filter(|t| !t.token.is_trivia()).collect();
+        let (_, diagnostics) = parse(tokens);
+        let messages = diagnostics.iter().map(|d| (d.msg.as_str(), d.span.line)).collect::<Vec<_>>();
+        assert_eq!(messages, [("unused doc comment", 2)]);
+    }
+
+    #[test]
+    fn a_stray_block_is_skipped_as_a_whole() {
+        let (ast, errors) = parse_src("
+fn pr(x: i32) {}
+{ let x = 100; pr(x); }
+fn main() { pr(1); }
+");
+        assert_eq!(errors, ["expected an item or statement, found `{`"]);
+        // both functions are still parsed
+        assert_eq!(ast.len(), 2);
+    }
+
+    #[test]
+    fn every_error_is_reported() {
+        let (ast, errors) = parse_src("
+fn f() { let a = ; let b = (1 + ); b; }
+fn g() { let c = 1 }
+fn h() {}
+");
+        assert_eq!(errors, ["expected a value, found `;`", "expected a value, found `)`", "expected `;`, found `}`"]);
+        assert_eq!(ast.len(), 3);
+    }
+
+    #[test]
+    fn malformed_input_never_panics() {
+        let src = r#"
+import "std.wat";
+/// a shape
+enum Shape { Circle(f32), Rect { w: i32, h: i32 }, Empty }
+struct Point { x: i32, y: i32 }
+fn area(s: Shape): i32 {
+    match s { Shape::Rect { w, h } => w * h, Shape::Circle(_) => 1, _ => 0 }
+}
+fn main() {
+    let mut p = Point { x: 1, y: -2 };
+    p.x += 2 ^ 3 as i32;
+    'outer: for i in 0..=10 {
+        while i < 3 && p.y != 0 { if i == 2 { break 'outer; } else { continue; } }
+    }
+    let c = 'a';
+    println("done\n");
+}
+"#;
+        // every prefix is an unfinished program, and so is every suffix
+        for (i, _) in src.char_indices() {
+            parse_src(&src[..i]);
+            parse_src(&src[i..]);
+        }
+    }
+}
@@ -1,3 +1,12 @@
+use std::fmt::Display;
+
+use crate::span::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
@@ -5,19 +14,35 @@ pub enum Token {
     Eof,
     WhiteSpace(String),
     NewLine,
+    /// a `//` or `/* */` comment, with the slashes and stars
+    Comment(String),
+    /// the text of a `///` comment, without the slashes. kept for the item after it
+    DocComment(String),
 
     LPeren,
     RPeren,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
 
     Plus,
     Minus,
     Star,
     Slash,
+    PlusEq,
+    MinusEq,
+    StarEq,
+    SlashEq,
+    Percent,
     Carret,
+    Tilde,
+    AndAnd,
+    OrOr,
     Coma,
     Dot,
+    DotDot,
+    DotDotEq,
     Eq,
     EqTo,
     NotEq,
@@ -26,29 +51,40 @@ pub enum Token {
     Lt,
     GtEq,
     LtEq,
+    Shl,
+    Shr,
     Colin,
+    ColinColin,
+    FatArrow,
     SemiColin,
-    Quote,
 
     Number(Number),
 
     String(String),
+    /// a char literal like `'a'`
+    Char(char),
 
     Ident(String),
+    /// a loop label like `'outer`, without the quote
+    Label(String),
 
 
     Let,
+    Mut,
     Fn,
     For,
+    In,
     While,
     If,
     Else,
     Struct,
     Enum,
+    Match,
     Return,
     Break,
     Continue,
     Import,
+    As,
 
     True,
     False
@@ -56,41 +92,100 @@ pub enum Token {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Number {
-    F32(f32),
-    I32(i32),
+    /// the digits and the type suffix, like `2.5f64`
+    Float(f64, Option<String>),
+    Int(u64, Option<String>),
 }
 
 impl Token {
     pub fn is_eof(&self) -> bool {
-        match self {
-            Token::Eof => true,
-            _ => false,
-        }
+        matches!(self, Token::Eof)
     }
     pub fn is_not_eof(&self) -> bool {
-        match self {
-            Token::Eof => false,
-            _ => true,
-        }
+        !self.is_eof()
     }
-    pub fn is_whitespace(&self) -> bool {
-        match self {
-            Token::WhiteSpace(_) => true,
-            _ => false,
-        }
+    /// tokens the parser never sees. doc comments aren't trivia, they belong to an item
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Token::WhiteSpace(_) | Token::Comment(_))
     }
-    pub fn is_ident(&self) -> bool {
-        match self {
-            Token::Ident(_) => true,
-            _ => false,
-        }
+    pub fn can_start_expr(&self) -> bool {
+        matches!(self,
+            Token::Ident(_) | Token::Number(_) | Token::String(_) | Token::Char(_) | Token::True | Token::False |
+            Token::LPeren | Token::If | Token::Match | Token::Minus | Token::Not | Token::Tilde
+        )
     }
-    pub fn is_operator(&self) -> bool {
-        match self {
-            Token::Plus | Token::Minus | Token::Slash | Token::Star |
-            Token::Gt | Token::Lt | Token::GtEq | Token::LtEq |
-            Token::Carret | Token::Not => true,
-            _ => false,
-        }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Token::Elegal => "illegal character",
+            Token::Eof => "end of file",
+            Token::WhiteSpace(_) => "whitespace",
+            Token::NewLine => "newline",
+            Token::Comment(_) => "comment",
+            Token::DocComment(_) => "doc comment",
+            Token::LPeren => "`(`",
+            Token::RPeren => "`)`",
+            Token::LBrace => "`{`",
+            Token::RBrace => "`}`",
+            Token::LBracket => "`[`",
+            Token::RBracket => "`]`",
+            Token::Plus => "`+`",
+            Token::Minus => "`-`",
+            Token::Star => "`*`",
+            Token::Slash => "`/`",
+            Token::PlusEq => "`+=`",
+            Token::MinusEq => "`-=`",
+            Token::StarEq => "`*=`",
+            Token::SlashEq => "`/=`",
+            Token::Percent => "`%`",
+            Token::Carret => "`^`",
+            Token::Tilde => "`~`",
+            Token::AndAnd => "`&&`",
+            Token::OrOr => "`||`",
+            Token::Coma => "`,`",
+            Token::Dot => "`.`",
+            Token::DotDot => "`..`",
+            Token::DotDotEq => "`..=`",
+            Token::Eq => "`=`",
+            Token::EqTo => "`==`",
+            Token::NotEq => "`!=`",
+            Token::Not => "`!`",
+            Token::Gt => "`>`",
+            Token::Lt => "`<`",
+            Token::GtEq => "`>=`",
+            Token::LtEq => "`<=`",
+            Token::Shl => "`<<`",
+            Token::Shr => "`>>`",
+            Token::Colin => "`:`",
+            Token::ColinColin => "`::`",
+            Token::FatArrow => "`=>`",
+            Token::SemiColin => "`;`",
+            Token::Number(_) => "number",
+            Token::String(_) => "string",
+            Token::Char(_) => "char",
+            Token::Ident(i) => return write!(f, "`{}`", i),
+            Token::Label(l) => return write!(f, "label `'{}`", l),
+            Token::Let => "`let`",
+            Token::Mut => "`mut`",
+            Token::Fn => "`fn`",
+            Token::For => "`for`",
+            Token::In => "`in`",
+            Token::While => "`while`",
+            Token::If => "`if`",
+            Token::Else => "`else`",
+            Token::Struct => "`struct`",
+            Token::Enum => "`enum`",
+            Token::Match => "`match`",
+            Token::Return => "`return`",
+            Token::Break => "`break`",
+            Token::Continue => "`continue`",
+            Token::Import => "`import`",
+            Token::As => "`as`",
+            Token::True => "`true`",
+            Token::False => "`false`",
+        };
+        write!(f, "{}", s)
     }
 }
@@ -0,0 +1,1441 @@
+use std::{collections::HashMap, fs};
+
+use crate::ast::{Arg, Assign, Ast, Bop, Enum, EnumLit, Expression, ExpressionKind, FnCall, For, Function, If, Let, Match, Pattern, PatternKind, Statement, StatementKind, Type, Uop, Value, VariantShape, While};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+use crate::wat::get_signatures;
+
+/// gives every expression in the functions of the program its type and checks
+/// each value fits where it is used. the compiler relies on `Expression::tp`
+/// being filled in, so it only runs when this reports no errors
+pub fn check(ast: &mut [Ast]) -> Vec<Diagnostic> {
+    let mut checker = TypeChecker::new(ast);
+    for section in ast.iter_mut() {
+        if let Ast::Statement(Statement { kind: StatementKind::Function(func), .. }) = section {
+            // an error stops checking the function it is in, but not the ones after it
+            if let Err(e) = checker.check_fn(func) {
+                checker.diagnostics.push(e);
+            }
+        }
+    }
+    checker.diagnostics
+}
+
+pub type TypeError = Diagnostic;
+
+#[derive(Clone)]
+struct Signature {
+    args: Vec<Type>,
+    ret: Type,
+    /// imported from a wat file, which only knows the wasm types of its functions
+    wasm: bool,
+    /// the signature in the source, `None` for imported functions
+    span: Option<Span>,
+}
+
+struct TypeChecker {
+    /// the signature of every function defined in or imported into the program
+    funcs: HashMap<String, Signature>,
+    structs: HashMap<String, Vec<Arg>>,
+    enums: HashMap<String, Enum>,
+    /// the variables in scope, innermost scope last
+    scopes: Vec<HashMap<String, Variable>>,
+    /// the type variables of the function being checked, see `TypeVar`
+    vars: Vec<TypeVar>,
+    /// how many loops are around the code being checked
+    loop_depth: usize,
+    /// return type of the function being checked
+    ret_tp: Type,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// a variable or param of the function being checked
+struct Variable {
+    tp: Type,
+    mutable: bool,
+    param: bool,
+    /// where it was declared
+    span: Span,
+    /// where it was first given a value, `None` for `let x;` until it is assigned
+    assigned: Option<Span>,
+    /// where a branch or loop body that doesn't always run assigned it, while
+    /// it isn't definitely assigned yet
+    maybe_assigned: Option<Span>,
+    /// the loop depth it was declared at
+    loop_depth: usize,
+}
+
+/// a variable a branch assigned: the index of its scope, its name, where it
+/// was assigned and whether it definitely was
+type Assigned = (usize, String, Span, bool);
+
+/// types that aren't known yet are type variables, named `?0`, `?1`... after
+/// their index in `TypeChecker::vars`. they are bound to a type when they meet
+/// one, and replaced by it once the whole function has been checked
+#[derive(Clone)]
+enum TypeVar {
+    /// the type of an integer literal, which becomes i32 if nothing says otherwise
+    Integer,
+    /// the type of a float literal, which becomes f32 if nothing says otherwise
+    Float,
+    /// the type of a variable declared without a type or a value
+    Unknown { name: String, span: Span },
+    Bound(Type),
+}
+
+impl TypeChecker {
+    fn new(ast: &[Ast]) -> TypeChecker {
+        let mut funcs = HashMap::new();
+        let mut structs = HashMap::new();
+        let mut enums = HashMap::new();
+        for section in ast {
+            let Ast::Statement(s) = section else {
+                continue;
+            };
+            match &s.kind {
+                StatementKind::Function(func) => {
+                    funcs.insert(func.name.clone(), Signature {
+                        args: func.args.iter().map(|a| a.tp.clone()).collect(),
+                        ret: func.ret_tp.clone(),
+                        wasm: false,
+                        span: Some(s.span.to(func.ret_span)),
+                    });
+                }
+                StatementKind::Struct(s) => {
+                    structs.insert(s.name.clone(), s.fields.clone());
+                }
+                StatementKind::Enum(e) => {
+                    enums.insert(e.name.clone(), e.clone());
+                }
+                StatementKind::Import(import) => {
+                    // a missing file is reported when the import is compiled
+                    let content = fs::read_to_string(&import.path).unwrap_or_default();
+                    for (name, (params, ret)) in get_signatures(&content) {
+                        funcs.entry(name).or_insert(Signature {
+                            args: params.iter().map(Type::new).collect(),
+                            ret: ret.map(Type::new).unwrap_or(Type::new("()")),
+                            wasm: true,
+                            span: None,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        TypeChecker {
+            funcs,
+            structs,
+            enums,
+            scopes: Vec::new(),
+            vars: Vec::new(),
+            loop_depth: 0,
+            ret_tp: Type::new("()"),
+            diagnostics: Vec::new(),
+        }
+    }
+    fn check_fn(&mut self, func: &mut Function) -> Result<(), TypeError> {
+        let returns_value = func.ret_tp.name != "()";
+        if returns_value && !always_returns(&func.body) {
+            return Err(Diagnostic::error("mismatched types", func.ret_span)
+                .with_span_label(format!("expected `{}` because of the return type", func.ret_tp.name))
+                .with_note(format!("`{}` can reach the end of its body without returning a value", func.name))
+                .with_help("end the body with a value or add a `return`"));
+        }
+        let params = func.args.iter().map(|arg| (arg.name.clone(), Variable {
+            tp: arg.tp.clone(),
+            mutable: false,
+            param: true,
+            span: arg.span,
+            assigned: Some(arg.span),
+            maybe_assigned: None,
+            loop_depth: 0,
+        }));
+        self.scopes = vec![params.collect()];
+        self.vars.clear();
+        self.loop_depth = 0;
+        self.ret_tp = func.ret_tp.clone();
+
+        let finishes = self.check_block(&mut func.body, HashMap::new())?.is_some();
+        if let Some(Ast::Expression(tail)) = func.body.last_mut() {
+            if returns_value && finishes {
+                let tp = tail.tp().clone();
+                self.expect_type(tail, &tp, &func.ret_tp)?;
+            }
+        }
+        self.settle_block(&mut func.body)
+    }
+    /// checks the sections of a block, with the variables a pattern binds in
+    /// scope. returns the type of the value the block ends in and where that
+    /// value is, or `None` if the block never reaches its end
+    fn check_block(&mut self, block: &mut [Ast], bindings: HashMap<String, Variable>) -> Result<Option<(Type, Option<Span>)>, TypeError> {
+        self.scopes.push(bindings);
+        let res = self.check_sections(block);
+        self.scopes.pop();
+        let tail = res?;
+        Ok((!never_finishes(block)).then_some(tail))
+    }
+    fn check_sections(&mut self, block: &mut [Ast]) -> Result<(Type, Option<Span>), TypeError> {
+        let len = block.len();
+        let mut tail = (Type::new("()"), None);
+        for (i, ast) in block.iter_mut().enumerate() {
+            match ast {
+                Ast::Expression(expr) if i + 1 == len => {
+                    tail = (self.infer(expr)?, Some(expr.span));
+                }
+                Ast::Expression(expr) | Ast::Statement(Statement { kind: StatementKind::Expression(expr), .. }) => {
+                    self.infer(expr)?;
+                }
+                Ast::Statement(statmt) => self.check_statement(statmt)?,
+            }
+        }
+        Ok(tail)
+    }
+    fn check_statement(&mut self, statmt: &mut Statement) -> Result<(), TypeError> {
+        match &mut statmt.kind {
+            StatementKind::Let(l) => self.check_let(l, statmt.span),
+            StatementKind::Assign(a) => self.check_assign(a),
+            StatementKind::Expression(expr) => self.infer(expr).map(|_| ()),
+            StatementKind::Return(value) => self.check_return(value, statmt.span),
+            StatementKind::Break(_) | StatementKind::Continue(_) => Ok(()),
+            StatementKind::Function(_) | StatementKind::Struct(_) | StatementKind::Enum(_) | StatementKind::Import(_) => {
+                Err(Diagnostic::error("items can only be declared at the top level", statmt.span))
+            }
+        }
+    }
+    fn check_let(&mut self, l: &mut Let, span: Span) -> Result<(), TypeError> {
+        match &mut l.value {
+            Some(value) => {
+                let value_tp = self.infer(value)?;
+                if self.shallow(&value_tp).name == "()" {
+                    return Err(Diagnostic::error(format!("`{}` cannot be bound to a value of type `()`", l.name), value.span));
+                }
+                if l.type_infered {
+                    l.tp = value_tp;
+                } else {
+                    self.expect_type(value, &value_tp, &l.tp)?;
+                }
+            }
+            None if l.type_infered => {
+                l.tp = self.new_var(TypeVar::Unknown {
+                    name: l.name.clone(),
+                    span,
+                });
+            }
+            None => {}
+        }
+        // declared after checking the value so the value can read a variable it shadows
+        let loop_depth = self.loop_depth;
+        self.scopes.last_mut().unwrap().insert(l.name.clone(), Variable {
+            tp: l.tp.clone(),
+            mutable: l.mutable,
+            param: false,
+            span,
+            assigned: l.value.is_some().then_some(span),
+            maybe_assigned: None,
+            loop_depth,
+        });
+        Ok(())
+    }
+    fn check_assign(&mut self, a: &mut Assign) -> Result<(), TypeError> {
+        let mut root = &a.target;
+        while let ExpressionKind::Field(value, _) = &root.kind {
+            root = value;
+        }
+        let ExpressionKind::Value(Value::Var(name)) = &root.kind else {
+            unreachable!("the parser only assigns to variables and their fields")
+        };
+        let name = name.clone();
+        let is_field = !matches!(a.target.kind, ExpressionKind::Value(_));
+        // the parser also has variables declared outside of any function in scope, which functions can't see
+        let Some(var) = self.lookup(&name) else {
+            return Err(Diagnostic::error(format!("unknown variable `{}`", name), root.span));
+        };
+        // `let x;` can be assigned once, as long as that isn't in a loop it could be repeated by
+        let first = var.assigned.is_none() && var.maybe_assigned.is_none() && var.loop_depth == self.loop_depth && !is_field;
+        if !var.mutable && !first {
+            let (msg, label, help) = if var.param {
+                (
+                    format!("cannot assign to immutable argument `{}`", name),
+                    "argument declared here",
+                    format!("bind it to a mutable variable first: `let mut {0} = {0};`", name),
+                )
+            } else {
+                (
+                    format!("cannot assign twice to immutable variable `{}`", name),
+                    "first assignment here",
+                    format!("consider making it mutable: `let mut {}`", name),
+                )
+            };
+            let declared = (var.span, format!("`{}` declared here", name));
+            let (msg, span_label, (span, label)) = match var.assigned.or(var.maybe_assigned) {
+                _ if is_field => (
+                    format!("cannot assign to `{}`, as `{}` is not declared as mutable", place_path(&a.target), name),
+                    "cannot be assigned to",
+                    declared,
+                ),
+                Some(first) => (msg, "cannot be assigned to", (first, label.to_string())),
+                // nothing assigned it before, but the loop around this assignment repeats it
+                None => (msg, "first assigned here, then again by the next iteration of the loop", declared),
+            };
+            return Err(Diagnostic::error(msg, a.target.span)
+                .with_span_label(span_label)
+                .with_label(span, label)
+                .with_help(help));
+        }
+        // assigning to the whole variable doesn't read it, so it doesn't have to be initialized
+        let whole = a.op.is_none() && !is_field;
+        let tp = if whole {
+            let tp = var.tp.clone();
+            a.target.tp = Some(tp.clone());
+            tp
+        } else {
+            self.infer(&mut a.target)?
+        };
+        match &a.op {
+            // `x += v` is checked like `x = x + v`
+            Some(op) => {
+                let mut target = a.target.clone();
+                let value_tp = self.infer_binary_op(&mut target, op, &mut a.value)?;
+                let span = a.target.span.to(a.value.span);
+                if self.unify(&value_tp, &tp).is_none() {
+                    return Err(Diagnostic::error("mismatched types", span)
+                        .with_span_label(format!("expected `{}`, found `{}`", self.show(&tp), self.show(&value_tp))));
+                }
+            }
+            None => {
+                self.check(&mut a.value, &tp)?;
+            }
+        }
+        // only marked once the value is checked, since `x = x + 1` reads `x` before assigning it
+        if whole {
+            let span = a.target.span;
+            let var = self.lookup_mut(&name).unwrap();
+            var.assigned = var.assigned.or(Some(span));
+        }
+        Ok(())
+    }
+    fn check_return(&mut self, value: &mut Option<Expression>, span: Span) -> Result<(), TypeError> {
+        let ret_tp = self.ret_tp.clone();
+        match value {
+            Some(value) => {
+                let tp = self.infer(value)?;
+                if ret_tp.name == "()" {
+                    return Err(Diagnostic::error("mismatched types", value.span)
+                        .with_span_label(format!("expected `()`, found `{}`", self.show(&tp)))
+                        .with_note("the function has no return type"));
+                }
+                self.expect_type(value, &tp, &ret_tp)
+            }
+            None if ret_tp.name != "()" => {
+                Err(Diagnostic::error("`return;` in a function that returns a value", span)
+                    .with_span_label(format!("expected a value of type `{}`", ret_tp.name)))
+            }
+            None => Ok(()),
+        }
+    }
+    fn lookup(&self, name: &str) -> Option<&Variable> {
+        self.scopes.iter().rev().find_map(|s| s.get(name))
+    }
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut Variable> {
+        self.scopes.iter_mut().rev().find_map(|s| s.get_mut(name))
+    }
+    /// the variables declared with `let x;` that aren't definitely assigned
+    /// yet, with where they may have been
+    fn unassigned(&self) -> Vec<(usize, String, Option<Span>)> {
+        self.scopes.iter().enumerate()
+            .flat_map(|(i, s)| s.iter().filter(|(_, v)| v.assigned.is_none()).map(move |(n, v)| (i, n.clone(), v.maybe_assigned)))
+            .collect()
+    }
+    /// puts the variables back the way `unassigned` found them, returning the
+    /// ones that were assigned since, where, and whether they definitely were.
+    /// used to check each branch of an `if` or `match` on its own
+    fn take_assigned(&mut self, vars: &[(usize, String, Option<Span>)]) -> Vec<Assigned> {
+        let mut assigned = Vec::new();
+        for (i, name, maybe) in vars {
+            let var = self.scopes[*i].get_mut(name).unwrap();
+            let definitely = var.assigned.take();
+            let now = std::mem::replace(&mut var.maybe_assigned, *maybe);
+            if let Some(span) = definitely.or(now.filter(|_| now != *maybe)) {
+                assigned.push((*i, name.clone(), span, definitely.is_some()));
+            }
+        }
+        assigned
+    }
+    /// marks the variables assigned by the branches as assigned after them.
+    /// `branches` has whether each branch finishes and what it assigned. a
+    /// variable is only definitely assigned if every branch that finishes
+    /// assigned it, otherwise it is maybe assigned
+    fn mark_assigned(&mut self, branches: Vec<(bool, Vec<Assigned>)>) {
+        let finishing = branches.iter().filter(|(finishes, _)| *finishes).map(|(_, a)| a).collect::<Vec<_>>();
+        for (i, name, span, _) in branches.iter().flat_map(|(_, a)| a) {
+            let everywhere = finishing.iter().all(|a| a.iter().any(|(j, n, _, definitely)| j == i && n == name && *definitely));
+            let var = self.scopes[*i].get_mut(name).unwrap();
+            if everywhere {
+                var.assigned = var.assigned.or(Some(*span));
+            }
+            var.maybe_assigned = var.maybe_assigned.or(Some(*span));
+        }
+    }
+    fn new_var(&mut self, var: TypeVar) -> Type {
+        self.vars.push(var);
+        Type::new(format!("?{}", self.vars.len() - 1))
+    }
+    /// the type a type variable is bound to, following variables bound to other variables
+    fn shallow(&self, tp: &Type) -> Type {
+        match var_id(tp).map(|id| &self.vars[id]) {
+            Some(TypeVar::Bound(bound)) => self.shallow(bound),
+            _ => tp.clone(),
+        }
+    }
+    fn var(&self, tp: &Type) -> Option<&TypeVar> {
+        var_id(&self.shallow(tp)).map(|id| &self.vars[id])
+    }
+    /// how a type is written in error messages, like rust does for types that aren't known yet
+    fn show(&self, tp: &Type) -> String {
+        match self.var(tp) {
+            Some(TypeVar::Integer) => String::from("{integer}"),
+            Some(TypeVar::Float) => String::from("{float}"),
+            Some(_) => String::from("_"),
+            None => self.shallow(tp).name,
+        }
+    }
+    fn is_integer(&self, tp: &Type) -> bool {
+        matches!(self.var(tp), Some(TypeVar::Integer)) || self.shallow(tp).is_integer()
+    }
+    fn is_signed(&self, tp: &Type) -> bool {
+        matches!(self.var(tp), Some(TypeVar::Integer)) || self.shallow(tp).is_signed()
+    }
+    fn is_float(&self, tp: &Type) -> bool {
+        matches!(self.var(tp), Some(TypeVar::Float)) || self.shallow(tp).is_float()
+    }
+    /// makes the two types the same by binding the type variables in them,
+    /// returning the type they now both are. `None` if they can't be
+    fn unify(&mut self, a: &Type, b: &Type) -> Option<Type> {
+        let (a, b) = (self.shallow(a), self.shallow(b));
+        if a.name == b.name {
+            return Some(a);
+        }
+        let (var, tp) = match (var_id(&a), var_id(&b)) {
+            // literals are more specific than unknown variables, so the unknown one is bound to them
+            (Some(_), Some(y)) if matches!(self.vars[y], TypeVar::Unknown { .. }) => (y, a),
+            (Some(x), _) => (x, b),
+            (None, Some(y)) => (y, a),
+            (None, None) => return None,
+        };
+        let fits = match (&self.vars[var], self.var(&tp)) {
+            (TypeVar::Unknown { .. }, _) => true,
+            (TypeVar::Integer, Some(TypeVar::Integer)) | (TypeVar::Float, Some(TypeVar::Float)) => true,
+            (_, Some(_)) => false,
+            (TypeVar::Integer, None) => tp.is_integer(),
+            (TypeVar::Float, None) => tp.is_float(),
+            (TypeVar::Bound(_), _) => unreachable!("`shallow` follows bound variables"),
+        };
+        if !fits {
+            return None;
+        }
+        self.vars[var] = TypeVar::Bound(tp.clone());
+        Some(tp)
+    }
+    /// errors unless a value of type `found` can be used where `expected` is
+    /// needed. numbers that fit in `expected` without losing anything are
+    /// widened to it, by wrapping `expr` in a cast
+    fn expect_type(&mut self, expr: &mut Expression, found: &Type, expected: &Type) -> Result<(), TypeError> {
+        if self.unify(found, expected).is_some() {
+            return Ok(());
+        }
+        let (from, to) = (self.shallow(found), self.shallow(expected));
+        if widens(&from, &to) {
+            let value = expr.clone();
+            *expr = Expression {
+                kind: ExpressionKind::Cast(Box::new(value), to.clone()),
+                span: expr.span,
+                tp: Some(to),
+            };
+            return Ok(());
+        }
+        Err(Diagnostic::error("mismatched types", expr.span)
+            .with_span_label(format!("expected `{}`, found `{}`", self.show(expected), self.show(found))))
+    }
+    /// errors if nothing has said what type `tp` is yet, for uses that need to know
+    fn known(&self, tp: &Type, span: Span) -> Result<Type, TypeError> {
+        match self.var(tp) {
+            Some(TypeVar::Unknown { name, span: declared }) => Err(Diagnostic::error("type annotations needed", span)
+                .with_span_label("type must be known at this point")
+                .with_label(*declared, format!("`{}` declared here", name))
+                .with_help(format!("give `{}` a type, like `let {}: i32;`", name, name))),
+            _ => Ok(self.shallow(tp)),
+        }
+    }
+    /// types the expression and checks it fits where `expected` is needed
+    fn check(&mut self, expr: &mut Expression, expected: &Type) -> Result<(), TypeError> {
+        let tp = self.infer(expr)?;
+        self.expect_type(expr, &tp, expected)
+    }
+    /// the condition of an `if` or `while`, which has to be a `bool`
+    fn check_condition(&mut self, cond: &mut Expression) -> Result<(), TypeError> {
+        let tp = self.infer(cond)?;
+        if self.unify(&tp, &Type::new("bool")).is_none() {
+            let err = Diagnostic::error("mismatched types", cond.span)
+                .with_span_label(format!("expected `bool`, found `{}`", self.show(&tp)));
+            if self.is_integer(&tp) {
+                return Err(err.with_help("compare it with `0` to check if it isn't zero, like `x != 0`"));
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+    /// types the expression and everything in it. types that aren't known yet
+    /// are type variables until the end of the function
+    fn infer(&mut self, expr: &mut Expression) -> Result<Type, TypeError> {
+        let span = expr.span;
+        let tp = match &mut expr.kind {
+            ExpressionKind::Value(v) => self.infer_value(v, span)?,
+            ExpressionKind::Field(obj, field) => {
+                self.infer(obj)?;
+                self.field(obj, field, span)?
+            }
+            ExpressionKind::BinaryOperation(lhs, op, rhs) => self.infer_binary_op(lhs, op, rhs)?,
+            ExpressionKind::UnaryOperation(op, value) => self.infer_unary_op(op, value)?,
+            ExpressionKind::If(i) => self.infer_if(i, span)?,
+            ExpressionKind::Match(m) => self.infer_match(m)?,
+            ExpressionKind::While(w) => {
+                self.check_while(w)?;
+                Type::new("()")
+            }
+            ExpressionKind::For(l) => {
+                self.check_for(l)?;
+                Type::new("()")
+            }
+            ExpressionKind::Range(..) => return Err(Diagnostic::error("ranges can only be used in `for` loops", span)),
+            ExpressionKind::Cast(value, tp) => self.check_cast(value, tp, span)?,
+            ExpressionKind::Index(value, index) => self.infer_index(value, index)?,
+            // like an `if` whose branches all never finish, a block that never finishes is `()`
+            ExpressionKind::Block(block) => match self.check_block(block, HashMap::new())? {
+                Some((tp, _)) => tp,
+                None => Type::new("()"),
+            },
+        };
+        expr.tp = Some(tp.clone());
+        Ok(tp)
+    }
+    fn infer_value(&mut self, value: &mut Value, span: Span) -> Result<Type, TypeError> {
+        Ok(match value {
+            Value::Bool(_) => Type::new("bool"),
+            Value::Char(_) => Type::new("char"),
+            Value::Float(_, Some(tp)) | Value::Int(_, Some(tp)) => tp.clone(),
+            Value::Float(_, None) => self.new_var(TypeVar::Float),
+            Value::Int(_, None) => self.new_var(TypeVar::Integer),
+            Value::TypeConstr(tp) => Type::new(&tp.name),
+            Value::Var(name) => match self.lookup(name) {
+                Some(Variable { assigned: None, maybe_assigned: Some(maybe), span: declared, .. }) => {
+                    return Err(Diagnostic::error(format!("used binding `{}` is possibly-uninitialized", name), span)
+                        .with_span_label(format!("`{}` used here but it isn't always initialized", name))
+                        .with_label(*declared, "declared here without a value")
+                        .with_label(*maybe, "only assigned here, which doesn't always run"));
+                }
+                Some(Variable { assigned: None, span: declared, .. }) => {
+                    return Err(Diagnostic::error(format!("used binding `{}` isn't initialized", name), span)
+                        .with_span_label(format!("`{}` used here but it isn't initialized", name))
+                        .with_label(*declared, "declared here without a value"));
+                }
+                Some(var) => var.tp.clone(),
+                None => return Err(Diagnostic::error(format!("unknown variable `{}`", name), span)),
+            },
+            Value::FnCall(call) => self.check_call(call, span)?,
+            Value::StructLit(lit) => {
+                let Some(fields) = self.structs.get(&lit.name).cloned() else {
+                    return Err(Diagnostic::error(format!("`{}` is not a struct", lit.name), span));
+                };
+                self.check_fields(&format!("struct `{}`", lit.name), &fields, &mut lit.fields, span)?;
+                Type::new(&lit.name)
+            }
+            Value::EnumLit(lit) => {
+                self.check_enum_lit(lit, span)?;
+                Type::new(&lit.name)
+            }
+        })
+    }
+    /// checks the arguments against the params of the function, returning what it returns
+    fn check_call(&mut self, call: &mut FnCall, span: Span) -> Result<Type, TypeError> {
+        let Some(sig) = self.funcs.get(&call.name).cloned() else {
+            // functions imported from wat files we couldn't read a signature for, assume i32
+            for arg in &mut call.args {
+                self.infer(arg)?;
+            }
+            return Ok(Type::new("i32"));
+        };
+        let defined_here = |err: Diagnostic| match sig.span {
+            Some(s) => err.with_span_note(s, "function defined here"),
+            None => err,
+        };
+        if call.args.len() != sig.args.len() {
+            let s = if sig.args.len() == 1 { "" } else { "s" };
+            let were = if call.args.len() == 1 { "was" } else { "were" };
+            return Err(defined_here(Diagnostic::error(format!(
+                "`{}` takes {} argument{} but {} {} supplied",
+                call.name, sig.args.len(), s, call.args.len(), were,
+            ), span)));
+        }
+        for (arg, param) in call.args.iter_mut().zip(&sig.args) {
+            if !sig.wasm {
+                self.check(arg, param).map_err(defined_here)?;
+                continue;
+            }
+            // anything passed as the right wasm type will do
+            let found = self.infer(arg)?;
+            let tp = match self.var(&found) {
+                Some(_) => self.unify(&found, param).unwrap_or(found),
+                None => self.shallow(&found),
+            };
+            if tp.wasm_type() != param.name {
+                return Err(Diagnostic::error("mismatched types", arg.span)
+                    .with_span_label(format!("expected `{}`, found `{}`", param.name, self.show(&tp))));
+            }
+        }
+        Ok(sig.ret)
+    }
+    fn check_enum_lit(&mut self, lit: &mut EnumLit, span: Span) -> Result<(), TypeError> {
+        let Some(e) = self.enums.get(&lit.name) else {
+            return Err(Diagnostic::error(format!("`{}` is not an enum", lit.name), span));
+        };
+        let Some(variant) = e.variants.iter().find(|v| v.name == lit.variant).cloned() else {
+            return Err(Diagnostic::error(format!("no variant named `{}` in enum `{}`", lit.variant, lit.name), span)
+                .with_span_label("variant not found"));
+        };
+        let path = format!("{}::{}", lit.name, lit.variant);
+        if lit.shape != variant.shape {
+            return Err(Diagnostic::error(format!("`{}` is a {} variant", path, shape_name(variant.shape)), span)
+                .with_help(format!("write it as `{}`", variant_syntax(&path, variant.shape))));
+        }
+        if variant.shape == VariantShape::Tuple && lit.fields.len() != variant.fields.len() {
+            return Err(Diagnostic::error(format!(
+                "`{}` takes {} field{} but {} were supplied",
+                path,
+                variant.fields.len(),
+                if variant.fields.len() == 1 { "" } else { "s" },
+                lit.fields.len(),
+            ), span));
+        }
+        self.check_fields(&format!("variant `{}`", path), &variant.fields, &mut lit.fields, span)
+    }
+    /// checks a struct or variant literal gives every field exactly once, with
+    /// a value of the field's type
+    fn check_fields(&mut self, what: &str, defs: &[Arg], fields: &mut [(String, Span, Expression)], span: Span) -> Result<(), TypeError> {
+        for (i, (field, field_span, _)) in fields.iter().enumerate() {
+            if let Some((_, prev, _)) = fields[..i].iter().find(|(f, _, _)| f == field) {
+                return Err(Diagnostic::error(format!("field `{}` specified more than once", field), *field_span)
+                    .with_span_label("used more than once")
+                    .with_label(*prev, format!("first use of `{}`", field)));
+            }
+            if !defs.iter().any(|d| &d.name == field) {
+                return Err(Diagnostic::error(format!("{} has no field named `{}`", what, field), *field_span)
+                    .with_span_label("unknown field"));
+            }
+        }
+        let missing = defs.iter()
+            .filter(|d| !fields.iter().any(|(name, _, _)| name == &d.name))
+            .map(|d| format!("`{}`", d.name))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let s = if missing.len() == 1 { "" } else { "s" };
+            return Err(Diagnostic::error(format!("missing field{} {} in initializer of {}", s, missing.join(", "), what), span));
+        }
+        for (field, _, value) in fields {
+            let def = defs.iter().find(|d| &d.name == field).unwrap();
+            self.check(value, &def.tp)?;
+        }
+        Ok(())
+    }
+    /// the type of a field of the struct `obj` evaluates to
+    fn field(&self, obj: &Expression, field: &str, span: Span) -> Result<Type, TypeError> {
+        let tp = self.known(obj.tp(), obj.span)?;
+        let Some(fields) = self.structs.get(&tp.name) else {
+            return Err(Diagnostic::error(format!("`{}` has no fields", self.show(&tp)), span)
+                .with_label(obj.span, format!("this is `{}`", self.show(&tp))));
+        };
+        match fields.iter().find(|f| f.name == field) {
+            Some(f) => Ok(f.tp.clone()),
+            None => {
+                let names = fields.iter().map(|f| format!("`{}`", f.name)).collect::<Vec<_>>();
+                Err(Diagnostic::error(format!("no field `{}` on type `{}`", field, tp.name), span)
+                    .with_span_label("unknown field")
+                    .with_help(format!("available fields are: {}", names.join(", "))))
+            }
+        }
+    }
+    /// `value as tp`, which converts between any two number types, or a `bool`
+    /// or `char` to an integer. only a `u8` can become a `char`, as every
+    /// value it can hold is a valid char
+    fn check_cast(&mut self, value: &mut Expression, tp: &Type, span: Span) -> Result<Type, TypeError> {
+        let from = self.infer(value)?;
+        let from = self.known(&from, value.span)?;
+        if tp.is_char() && self.is_integer(&from) {
+            if from.name == "u8" {
+                return Ok(tp.clone());
+            }
+            return Err(Diagnostic::error(format!("only `u8` can be cast as `char`, not `{}`", self.show(&from)), span)
+                .with_note("not every integer is a valid unicode scalar value"));
+        }
+        let is_number = |t: &Type| self.is_integer(t) || self.is_float(t);
+        let allowed = is_number(tp) && is_number(&from)
+            || tp.is_integer() && (from.is_bool() || from.is_char())
+            || tp.is_char() && from.is_char();
+        if !allowed {
+            return Err(Diagnostic::error(format!("non-primitive cast: `{}` as `{}`", self.show(&from), tp.name), span)
+                .with_note("only numbers, `bool` and `char` can be cast with `as`"));
+        }
+        Ok(tp.clone())
+    }
+    /// `string[index]`, the char `index` chars into the string
+    fn infer_index(&mut self, value: &mut Expression, index: &mut Expression) -> Result<Type, TypeError> {
+        let tp = self.infer(value)?;
+        let tp = self.known(&tp, value.span)?;
+        if tp.name != "String" {
+            return Err(Diagnostic::error(format!("cannot index into a value of type `{}`", self.show(&tp)), value.span)
+                .with_note("only strings can be indexed"));
+        }
+        self.check(index, &Type::new("i32"))?;
+        Ok(Type::new("char"))
+    }
+    fn infer_unary_op(&mut self, op: &Uop, value: &mut Expression) -> Result<Type, TypeError> {
+        let tp = self.infer(value)?;
+        if matches!(op, Uop::Not) {
+            if self.unify(&tp, &Type::new("bool")).is_none() {
+                let err = Diagnostic::error(format!("cannot apply `!` to `{}`", self.show(&tp)), value.span);
+                if self.is_integer(&tp) {
+                    return Err(err.with_help("use `~` to flip the bits of an integer"));
+                }
+                return Err(err);
+            }
+            return Ok(Type::new("bool"));
+        }
+        let tp = self.known(&tp, value.span)?;
+        let allowed = match op {
+            Uop::Neg => self.is_integer(&tp) || self.is_float(&tp),
+            Uop::BitNot => self.is_integer(&tp),
+            Uop::Not => unreachable!("`!` is checked above"),
+        };
+        if !allowed {
+            return Err(Diagnostic::error(format!("cannot apply `{}` to `{}`", op, self.show(&tp)), value.span));
+        }
+        // an integer literal that is negated is checked to be signed once its type is known
+        if matches!(op, Uop::Neg) && !self.is_signed(&tp) && !self.is_float(&tp) {
+            return Err(Diagnostic::error(format!("cannot negate unsigned type `{}`", tp.name), value.span));
+        }
+        Ok(tp)
+    }
+    fn infer_binary_op(&mut self, lhs: &mut Expression, op: &Bop, rhs: &mut Expression) -> Result<Type, TypeError> {
+        let bool_tp = Type::new("bool");
+        if op.is_logical() {
+            self.check(lhs, &bool_tp)?;
+            self.check(rhs, &bool_tp)?;
+            return Ok(bool_tp);
+        }
+        self.infer(lhs)?;
+        let rhs_tp = self.infer(rhs)?;
+        // `string + c` is a copy of the string with the char pushed onto the end
+        if matches!(op, Bop::Plus) && self.shallow(lhs.tp()).name == "String" {
+            self.expect_type(rhs, &rhs_tp, &Type::new("char"))?;
+            return Ok(Type::new("String"));
+        }
+        let tp = self.operand_type(lhs, rhs)?;
+        let span = lhs.span.to(rhs.span);
+        let tp = self.known(&tp, span)?;
+        if tp.is_bool() && matches!(op, Bop::Eq | Bop::NotEq) || tp.is_char() && op.is_comparison() {
+            return Ok(bool_tp);
+        }
+        if !self.is_integer(&tp) && !self.is_float(&tp) {
+            return Err(Diagnostic::error(format!("cannot apply `{}` to `{}`", op, tp.name), span)
+                .with_span_label("only numbers have operators"));
+        }
+        if self.is_float(&tp) && matches!(op, Bop::Rem | Bop::Shl | Bop::Shr | Bop::Carret) {
+            return Err(Diagnostic::error(format!("`{}` is only supported for integers", op), span));
+        }
+        if matches!(op, Bop::Slash | Bop::Rem) && matches!(rhs.kind, ExpressionKind::Value(Value::Int(0, _))) {
+            return Err(Diagnostic::error("attempt to divide by zero", rhs.span));
+        }
+        if op.is_comparison() {
+            return Ok(bool_tp);
+        }
+        Ok(tp)
+    }
+    /// the type both sides of a binary operation share
+    fn operand_type(&mut self, lhs: &Expression, rhs: &Expression) -> Result<Type, TypeError> {
+        let (l, r) = (lhs.tp(), rhs.tp());
+        match self.unify(l, r) {
+            Some(tp) => Ok(tp),
+            None => Err(Diagnostic::error(format!("mismatched types `{}` and `{}`", self.show(l), self.show(r)), lhs.span.to(rhs.span))
+                .with_label(lhs.span, format!("this is `{}`", self.show(l)))
+                .with_label(rhs.span, format!("this is `{}`", self.show(r)))),
+        }
+    }
+    /// the type the branches of an `if` agree on. branches that never finish,
+    /// because they `return` or `break`, fit any type
+    fn infer_if(&mut self, i: &mut If, span: Span) -> Result<Type, TypeError> {
+        self.check_condition(&mut i.condition)?;
+        for elsif in i.elsifs.iter_mut().flatten() {
+            self.check_condition(&mut elsif.condition)?;
+        }
+        let has_else = i.els.is_some();
+        let mut branches = vec![&mut i.block];
+        branches.extend(i.elsifs.iter_mut().flatten().map(|e| &mut e.block));
+        branches.extend(&mut i.els);
+
+        let unassigned = self.unassigned();
+        let mut assigned = Vec::new();
+        let mut types = Vec::new();
+        for branch in branches {
+            let branch_tp = self.check_block(branch, HashMap::new())?;
+            assigned.push((branch_tp.is_some(), self.take_assigned(&unassigned)));
+            let Some((branch_tp, branch_span)) = branch_tp else {
+                continue;
+            };
+            let branch_span = branch_span.unwrap_or(span);
+            if !has_else && self.shallow(&branch_tp).name != "()" {
+                return Err(Diagnostic::error("`if` may be missing an `else` clause", span)
+                    .with_label(branch_span, format!("this makes the `if` produce `{}`", self.show(&branch_tp)))
+                    .with_help("add an `else` producing the same type"));
+            }
+            types.push((branch_tp, branch_span));
+        }
+        // without an `else`, nothing is assigned when the condition is false
+        if !has_else {
+            assigned.push((true, Vec::new()));
+        }
+        self.mark_assigned(assigned);
+        self.unify_branches(types, "`if` and `else` have incompatible types")
+    }
+    /// the type the arms of a `match` agree on
+    fn infer_match(&mut self, m: &mut Match) -> Result<Type, TypeError> {
+        let value_tp = self.infer(&mut m.value)?;
+        let value_tp = self.known(&value_tp, m.value.span)?;
+        self.check_arms(m, &value_tp)?;
+
+        let unassigned = self.unassigned();
+        let mut assigned = Vec::new();
+        let mut types = Vec::new();
+        for arm in &mut m.arms {
+            let bindings = self.pattern_bindings(&arm.pattern, &value_tp)?;
+            let arm_tp = self.check_block(&mut arm.body, bindings)?;
+            assigned.push((arm_tp.is_some(), self.take_assigned(&unassigned)));
+            if let Some((arm_tp, arm_span)) = arm_tp {
+                types.push((arm_tp, arm_span.unwrap_or(arm.pattern.span)));
+            }
+        }
+        self.mark_assigned(assigned);
+        self.unify_branches(types, "`match` arms have incompatible types")
+    }
+    /// the type the branches of an `if` or `match` agree on. `msg` is the error
+    /// when two of them don't
+    fn unify_branches(&mut self, branches: Vec<(Type, Span)>, msg: &str) -> Result<Type, TypeError> {
+        let mut tp: Option<(Type, Span)> = None;
+        for (branch_tp, branch_span) in branches {
+            tp = match tp {
+                None => Some((branch_tp, branch_span)),
+                Some((t, s)) => match self.unify(&t, &branch_tp) {
+                    Some(t) => Some((t, s)),
+                    None => {
+                        return Err(Diagnostic::error(msg, branch_span)
+                            .with_span_label(format!("expected `{}`, found `{}`", self.show(&t), self.show(&branch_tp)))
+                            .with_label(s, format!("this is `{}`", self.show(&t))));
+                    }
+                },
+            };
+        }
+        Ok(tp.map(|(t, _)| t).unwrap_or(Type::new("()")))
+    }
+    /// checks the pattern can match a value of type `tp` and returns the
+    /// variables it binds
+    fn pattern_bindings(&self, pattern: &Pattern, tp: &Type) -> Result<HashMap<String, Variable>, TypeError> {
+        let binding = |tp: &Type, span| Variable {
+            tp: tp.clone(),
+            mutable: false,
+            param: false,
+            span,
+            assigned: Some(span),
+            maybe_assigned: None,
+            loop_depth: self.loop_depth,
+        };
+        let (name, variant, shape, fields, rest) = match &pattern.kind {
+            PatternKind::Wildcard => return Ok(HashMap::new()),
+            PatternKind::Binding(name) => return Ok(HashMap::from([(name.clone(), binding(tp, pattern.span))])),
+            PatternKind::Variant { name, variant, shape, fields, rest } => (name, variant, *shape, fields, *rest),
+        };
+        if name != &tp.name {
+            return Err(Diagnostic::error("mismatched types", pattern.span)
+                .with_span_label(format!("expected `{}`, found `{}`", self.show(tp), name)));
+        }
+        let Some(e) = self.enums.get(name) else {
+            return Err(Diagnostic::error(format!("`{}` is not an enum", name), pattern.span));
+        };
+        let Some(def) = e.variants.iter().find(|v| &v.name == variant) else {
+            return Err(Diagnostic::error(format!("no variant named `{}` in enum `{}`", variant, name), pattern.span)
+                .with_span_label("variant not found"));
+        };
+        let path = format!("{}::{}", name, variant);
+        if shape != def.shape {
+            return Err(Diagnostic::error(format!("`{}` is a {} variant", path, shape_name(def.shape)), pattern.span)
+                .with_help(format!("match it with `{}`", variant_syntax(&path, def.shape))));
+        }
+        let expected = def.fields.len();
+        if shape == VariantShape::Tuple && (fields.len() > expected || (!rest && fields.len() < expected)) {
+            let s = |n: usize| if n == 1 { "" } else { "s" };
+            return Err(Diagnostic::error(format!(
+                "this pattern has {} field{}, but `{}` has {} field{}",
+                fields.len(), s(fields.len()), path, expected, s(expected),
+            ), pattern.span));
+        }
+        let missing = def.fields.iter()
+            .filter(|f| !fields.iter().any(|(name, _)| name == &f.name))
+            .map(|f| format!("`{}`", f.name))
+            .collect::<Vec<_>>();
+        if !rest && !missing.is_empty() {
+            let s = if missing.len() == 1 { "" } else { "s" };
+            return Err(Diagnostic::error(format!("pattern does not mention field{} {}", s, missing.join(", ")), pattern.span)
+                .with_help("include the missing fields, or ignore them with `..`"));
+        }
+
+        let mut bindings: HashMap<String, Variable> = HashMap::new();
+        for (field, sub) in fields {
+            let Some(def) = def.fields.iter().find(|f| &f.name == field) else {
+                return Err(Diagnostic::error(format!("variant `{}` does not have a field named `{}`", path, field), sub.span)
+                    .with_span_label("unknown field"));
+            };
+            match &sub.kind {
+                PatternKind::Wildcard => {}
+                PatternKind::Binding(b) => {
+                    if let Some(prev) = bindings.get(b) {
+                        return Err(Diagnostic::error(format!("identifier `{}` is bound more than once in the same pattern", b), sub.span)
+                            .with_span_label("used more than once")
+                            .with_label(prev.span, format!("first binding of `{}`", b)));
+                    }
+                    bindings.insert(b.clone(), binding(&def.tp, sub.span));
+                }
+                PatternKind::Variant { .. } => {
+                    return Err(Diagnostic::error("nested patterns are not supported yet", sub.span)
+                        .with_help("bind the field to a name and `match` on that in the arm"));
+                }
+            }
+        }
+        Ok(bindings)
+    }
+    /// warns about arms that can never be reached and errors if some value
+    /// isn't matched by any arm
+    fn check_arms(&mut self, m: &Match, tp: &Type) -> Result<(), TypeError> {
+        let variants = self.enums.get(&tp.name).map(|e| e.variants.clone()).unwrap_or_default();
+        let mut covered = vec![false; variants.len()];
+        let mut catch_all = false;
+        for arm in &m.arms {
+            self.pattern_bindings(&arm.pattern, tp)?;
+            let tag = match &arm.pattern.kind {
+                PatternKind::Variant { variant, .. } => variants.iter().position(|v| &v.name == variant),
+                _ => None,
+            };
+            let reachable = !catch_all && tag.is_none_or(|t| !covered[t]);
+            if !reachable {
+                self.diagnostics.push(Diagnostic::warning("unreachable pattern", arm.pattern.span)
+                    .with_span_label("no value can reach this arm")
+                    .with_note("every value it matches is matched by an arm before it"));
+            }
+            match tag {
+                Some(t) => covered[t] = true,
+                None => catch_all = true,
+            }
+        }
+        if catch_all || (self.enums.contains_key(&tp.name) && covered.iter().all(|c| *c)) {
+            return Ok(());
+        }
+        let missing = if self.enums.contains_key(&tp.name) {
+            variants.iter().zip(&covered)
+                .filter(|(_, c)| !**c)
+                .map(|(v, _)| format!("`{}`", variant_syntax(&format!("{}::{}", tp.name, v.name), v.shape)))
+                .collect::<Vec<_>>()
+        } else {
+            vec![String::from("`_`")]
+        };
+        let s = if missing.len() == 1 { "" } else { "s" };
+        Err(Diagnostic::error(format!("non-exhaustive patterns: {} not covered", missing.join(", ")), m.value.span)
+            .with_span_label(format!("pattern{} {} not covered", s, missing.join(", ")))
+            .with_help("add an arm for every missing variant, or a `_` arm matching everything else"))
+    }
+    fn check_while(&mut self, w: &mut While) -> Result<(), TypeError> {
+        self.check_condition(&mut w.condition)?;
+        self.check_loop_body(&mut w.block, HashMap::new())
+    }
+    fn check_for(&mut self, l: &mut For) -> Result<(), TypeError> {
+        let ExpressionKind::Range(start, end, _) = &mut l.iter.kind else {
+            let tp = self.infer(&mut l.iter)?;
+            return Err(Diagnostic::error(format!("`for` loops over `{}` are not supported yet", self.show(&tp)), l.iter.span)
+                .with_help("loop over a range like `0..n`"));
+        };
+        self.infer(start)?;
+        self.infer(end)?;
+        let tp = self.operand_type(start, end)?;
+        let tp = self.known(&tp, l.iter.span)?;
+        if !self.is_integer(&tp) {
+            return Err(Diagnostic::error(format!("ranges of `{}` are not supported", tp.name), l.iter.span)
+                .with_span_label("only integer ranges can be iterated"));
+        }
+        l.iter.tp = Some(tp.clone());
+
+        let var = HashMap::from([(l.var.clone(), Variable {
+            tp,
+            mutable: false,
+            param: false,
+            span: l.var_span,
+            assigned: Some(l.var_span),
+            maybe_assigned: None,
+            loop_depth: self.loop_depth + 1,
+        })]);
+        self.check_loop_body(&mut l.block, var)
+    }
+    /// the body of a loop, which may not run at all. so what it assigns is
+    /// only maybe assigned after the loop
+    fn check_loop_body(&mut self, block: &mut [Ast], bindings: HashMap<String, Variable>) -> Result<(), TypeError> {
+        let unassigned = self.unassigned();
+        self.loop_depth += 1;
+        let res = self.check_block(block, bindings);
+        self.loop_depth -= 1;
+        res?;
+        let assigned = self.take_assigned(&unassigned);
+        self.mark_assigned(vec![(true, assigned), (true, Vec::new())]);
+        Ok(())
+    }
+    /// replaces the type variables in the types of a checked function with
+    /// the types they were bound to. integer literals nothing gave a type to
+    /// become i32, and float literals f32
+    fn settle_block(&self, block: &mut [Ast]) -> Result<(), TypeError> {
+        for ast in block {
+            match ast {
+                Ast::Expression(expr) => self.settle(expr)?,
+                Ast::Statement(statmt) => match &mut statmt.kind {
+                    StatementKind::Let(l) => {
+                        l.tp = self.settle_type(&l.tp)?;
+                        if let Some(value) = &mut l.value {
+                            self.settle(value)?;
+                        }
+                    }
+                    StatementKind::Assign(a) => {
+                        self.settle(&mut a.target)?;
+                        self.settle(&mut a.value)?;
+                    }
+                    StatementKind::Expression(expr) | StatementKind::Return(Some(expr)) => self.settle(expr)?,
+                    _ => {}
+                },
+            }
+        }
+        Ok(())
+    }
+    fn settle(&self, expr: &mut Expression) -> Result<(), TypeError> {
+        let tp = self.settle_type(expr.tp())?;
+        match &mut expr.kind {
+            ExpressionKind::Value(Value::FnCall(call)) => {
+                for arg in &mut call.args {
+                    self.settle(arg)?;
+                }
+            }
+            ExpressionKind::Value(Value::StructLit(lit)) => {
+                for (_, _, value) in &mut lit.fields {
+                    self.settle(value)?;
+                }
+            }
+            ExpressionKind::Value(Value::EnumLit(lit)) => {
+                for (_, _, value) in &mut lit.fields {
+                    self.settle(value)?;
+                }
+            }
+            ExpressionKind::Value(Value::Int(i, _)) => {
+                let (min, max) = tp.int_range();
+                if *i < min || *i > max {
+                    return Err(Diagnostic::error(format!("literal out of range for `{}`", tp.name), expr.span)
+                        .with_span_label(format!("the literal `{}` does not fit into the type `{}` whose range is `{}..={}`", i, tp.name, min, max)));
+                }
+            }
+            ExpressionKind::Value(Value::Float(f, _)) => {
+                if tp.name == "f32" && f.abs() > f32::MAX as f64 {
+                    return Err(Diagnostic::error("literal out of range for `f32`", expr.span)
+                        .with_span_label(format!("the literal `{}` does not fit into the type `f32`", f)));
+                }
+            }
+            ExpressionKind::Value(_) => {}
+            ExpressionKind::Field(obj, _) | ExpressionKind::Cast(obj, _) => self.settle(obj)?,
+            ExpressionKind::BinaryOperation(lhs, op, rhs) => {
+                self.settle(lhs)?;
+                self.settle(rhs)?;
+                // operations on two integer literals are done here, so they can't overflow at runtime
+                if let (ExpressionKind::Value(Value::Int(l, _)), ExpressionKind::Value(Value::Int(r, _))) = (&lhs.kind, &rhs.kind) {
+                    if !op.is_comparison() {
+                        let value = fold(*l, op, *r, &tp, expr.span)?;
+                        expr.kind = ExpressionKind::Value(Value::Int(value, None));
+                    }
+                }
+            }
+            ExpressionKind::Range(lhs, rhs, _) | ExpressionKind::Index(lhs, rhs) => {
+                self.settle(lhs)?;
+                self.settle(rhs)?;
+            }
+            ExpressionKind::UnaryOperation(op, value) => {
+                if matches!(op, Uop::Neg) && tp.is_integer() && !tp.is_signed() {
+                    return Err(Diagnostic::error(format!("cannot negate unsigned type `{}`", tp.name), expr.span));
+                }
+                self.settle(value)?;
+            }
+            ExpressionKind::If(i) => {
+                self.settle(&mut i.condition)?;
+                self.settle_block(&mut i.block)?;
+                for elsif in i.elsifs.iter_mut().flatten() {
+                    self.settle(&mut elsif.condition)?;
+                    self.settle_block(&mut elsif.block)?;
+                }
+                if let Some(els) = &mut i.els {
+                    self.settle_block(els)?;
+                }
+            }
+            ExpressionKind::Match(m) => {
+                self.settle(&mut m.value)?;
+                for arm in &mut m.arms {
+                    self.settle_block(&mut arm.body)?;
+                }
+            }
+            ExpressionKind::While(w) => {
+                self.settle(&mut w.condition)?;
+                self.settle_block(&mut w.block)?;
+            }
+            ExpressionKind::For(l) => {
+                self.settle(&mut l.iter)?;
+                self.settle_block(&mut l.block)?;
+            }
+            ExpressionKind::Block(block) => self.settle_block(block)?,
+        }
+        expr.tp = Some(tp);
+        Ok(())
+    }
+    fn settle_type(&self, tp: &Type) -> Result<Type, TypeError> {
+        match self.var(tp) {
+            Some(TypeVar::Integer) => Ok(Type::new("i32")),
+            Some(TypeVar::Float) => Ok(Type::new("f32")),
+            Some(TypeVar::Unknown { name, span }) => Err(Diagnostic::error("type annotations needed", *span)
+                .with_span_label(format!("cannot infer the type of `{}`", name))
+                .with_help(format!("give `{}` a type, like `let {}: i32;`", name, name))),
+            _ => Ok(self.shallow(tp)),
+        }
+    }
+}
+
+/// the value of `l op r` for two integer literals of type `tp`, computed the
+/// way wasm would. errors if it doesn't fit in `tp`
+fn fold(l: i128, op: &Bop, r: i128, tp: &Type, span: Span) -> Result<i128, TypeError> {
+    if matches!(op, Bop::Slash | Bop::Rem) && r == 0 {
+        return Err(Diagnostic::error("attempt to divide by zero", span));
+    }
+    let value = match op {
+        Bop::Plus => l.checked_add(r),
+        Bop::Minus => l.checked_sub(r),
+        Bop::Star => l.checked_mul(r),
+        Bop::Slash => l.checked_div(r),
+        Bop::Rem => l.checked_rem(r),
+        // bits shifted out of the type are lost, like they are at runtime
+        Bop::Shl if (0..tp.bits() as i128).contains(&r) => Some(wrap_int(l << r, tp)),
+        Bop::Shr if (0..tp.bits() as i128).contains(&r) => Some(l >> r),
+        Bop::Shl | Bop::Shr => None,
+        // negative exponents truncate towards zero like division does
+        Bop::Carret if r < 0 => Some(match l {
+            1 => 1,
+            -1 if r % 2 == 0 => 1,
+            -1 => -1,
+            _ => 0,
+        }),
+        Bop::Carret => u32::try_from(r).ok().and_then(|r| l.checked_pow(r)),
+        Bop::Eq | Bop::NotEq | Bop::Gt | Bop::Lt | Bop::GtEq | Bop::LtEq | Bop::And | Bop::Or => {
+            unreachable!("comparisons aren't folded")
+        }
+    };
+    let (min, max) = tp.int_range();
+    match value {
+        Some(v) if (min..=max).contains(&v) => Ok(v),
+        _ => Err(Diagnostic::error("this arithmetic operation will overflow", span)
+            .with_span_label(format!("attempt to compute `{0}_{2} {1} {3}_{2}`, which would overflow", l, op, tp.name, r))),
+    }
+}
+/// `value` truncated to the bits of `tp`
+fn wrap_int(value: i128, tp: &Type) -> i128 {
+    let bits = tp.bits();
+    let value = value & ((1 << bits) - 1);
+    if tp.is_signed() && value >> (bits - 1) == 1 {
+        value - (1 << bits)
+    } else {
+        value
+    }
+}
+/// whether a value of type `from` can be used as a `to` without a cast.
+/// integers widen to bigger integers that hold all their values, and f32 to
+/// f64. operators never widen their operands, so `a + b` needs both to be the
+/// same type
+fn widens(from: &Type, to: &Type) -> bool {
+    if from.is_integer() && to.is_integer() {
+        return from.bits() < to.bits() && (from.is_signed() == to.is_signed() || to.is_signed());
+    }
+    from.name == "f32" && to.name == "f64"
+}
+/// the index of a type variable in `TypeChecker::vars`
+fn var_id(tp: &Type) -> Option<usize> {
+    tp.name.strip_prefix('?')?.parse().ok()
+}
+
+/// whether every path through a function body ends in a `return` or a value
+fn always_returns(body: &[Ast]) -> bool {
+    let ends_in_value = match body.last() {
+        Some(Ast::Expression(Expression { kind: ExpressionKind::If(i), .. })) => all_branches(i, always_returns),
+        Some(Ast::Expression(Expression { kind: ExpressionKind::Match(m), .. })) => m.arms.iter().all(|a| always_returns(&a.body)),
+        Some(Ast::Expression(Expression { kind: ExpressionKind::Block(block), .. })) => always_returns(block),
+        Some(Ast::Expression(e)) => !e.kind.is_block_like(),
+        _ => false,
+    };
+    ends_in_value || always_diverges(body)
+}
+/// whether every path through the block hits a `return`
+fn always_diverges(block: &[Ast]) -> bool {
+    block.iter().any(|ast| match ast {
+        Ast::Statement(s) => matches!(s.kind, StatementKind::Return(_)),
+        Ast::Expression(Expression { kind: ExpressionKind::If(i), .. }) => all_branches(i, always_diverges),
+        Ast::Expression(Expression { kind: ExpressionKind::Match(m), .. }) => m.arms.iter().all(|a| always_diverges(&a.body)),
+        Ast::Expression(Expression { kind: ExpressionKind::Block(block), .. }) => always_diverges(block),
+        Ast::Expression(_) => false,
+    })
+}
+/// whether every path through the block hits a `return`, `break` or `continue`
+fn never_finishes(block: &[Ast]) -> bool {
+    let jumps = |ast: &Ast| match ast {
+        Ast::Statement(Statement { kind: StatementKind::Break(_) | StatementKind::Continue(_), .. }) => true,
+        Ast::Expression(Expression { kind: ExpressionKind::Block(block), .. }) => never_finishes(block),
+        _ => false,
+    };
+    always_diverges(block) || block.iter().any(jumps)
+}
+/// whether `f` holds for every branch of an `if` that has an `else`
+fn all_branches(i: &If, f: fn(&[Ast]) -> bool) -> bool {
+    f(&i.block)
+        && i.elsifs.iter().flatten().all(|e| f(&e.block))
+        && i.els.as_ref().is_some_and(|els| f(els))
+}
+
+fn shape_name(shape: VariantShape) -> &'static str {
+    match shape {
+        VariantShape::Unit => "unit",
+        VariantShape::Tuple => "tuple",
+        VariantShape::Named => "struct",
+    }
+}
+/// how a variant is written in a pattern matching all of it, like `Shape::Rect { .. }`
+fn variant_syntax(path: &str, shape: VariantShape) -> String {
+    match shape {
+        VariantShape::Unit => path.to_string(),
+        VariantShape::Tuple => format!("{}(..)", path),
+        VariantShape::Named => format!("{} {{ .. }}", path),
+    }
+}
+/// `p.x.y` for an assignment target
+fn place_path(expr: &Expression) -> String {
+    match &expr.kind {
+        ExpressionKind::Value(Value::Var(name)) => name.clone(),
+        ExpressionKind::Field(value, field) => format!("{}.{}", place_path(value), field),
+        _ => expr.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check;
+    use crate::{diagnostic::{Diagnostic, Severity}, lexer::lex, parser::parse};
+
+    fn check_src(src: &str) -> Vec<Diagnostic> {
+        let (tokens, diagnostics) = lex(src);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let tokens = tokens.into_iter().filter(|t| !t.token.is_trivia()).collect();
+        let (mut ast, diagnostics) = parse(tokens);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        check(&mut ast)
+    }
+    fn errors(src: &str) -> Vec<String> {
+        check_src(src).into_iter().filter(|d| d.is_error()).map(|d| d.msg).collect()
+    }
+
+    #[test]
+    fn call_arguments_are_checked_against_the_params() {
+        let src = "
+fn add(a: i32, b: i64): i64 { b }
+fn main() { add(1, 2.0); }
+fn other() { add(1); }
+";
+        // every function is checked, even after an error in one before it
+        assert_eq!(errors(src), ["mismatched types", "`add` takes 2 arguments but 1 was supplied"]);
+        for diagnostic in check_src(src) {
+            let note = &diagnostic.notes[0];
+            assert_eq!((note.severity, note.msg.as_str(), note.span.map(|s| s.line)), (Severity::Note, "function defined here", Some(2)));
+        }
+    }
+
+    #[test]
+    fn return_values_and_operands_are_checked() {
+        assert_eq!(errors("fn f(): i32 { return 1.5; }"), ["mismatched types"]);
+        assert_eq!(errors("fn f(a: i32, b: i64): i64 { a + b }"), ["mismatched types `i32` and `i64`"]);
+        assert!(errors("fn f(a: u8): u8 { a * 2 + 1 }").is_empty());
+    }
+
+    #[test]
+    fn let_types_are_inferred_from_how_they_are_used() {
+        assert!(errors("fn f(a: i64): i64 { let b = 2; a + b * 0 + 1 }").is_empty());
+        assert!(errors("fn f(): i64 { let x = 5; let y: i64 = x; y }").is_empty());
+        assert_eq!(errors("fn f(): i64 { let x = 1.5; let y: i64 = x; y }"), ["mismatched types"]);
+        assert!(errors("fn f(c: bool): u8 { let x; if c { x = 1; } else { x = 2; } x }").is_empty());
+        assert_eq!(errors("fn f() { let x; }"), ["type annotations needed"]);
+        assert_eq!(errors("fn f(): i32 { let x: i32; x }"), ["used binding `x` isn't initialized"]);
+        assert_eq!(
+            errors("fn f() { let x; while true { x = 1; } }"),
+            ["cannot assign twice to immutable variable `x`"],
+        );
+    }
+
+    #[test]
+    fn variables_outside_of_functions_are_unknown_in_them() {
+        assert_eq!(errors("let g = 1; fn main() { g = 2; }"), ["unknown variable `g`"]);
+        assert_eq!(errors("let g = 1; fn main() { let x = g; }"), ["unknown variable `g`"]);
+    }
+
+    #[test]
+    fn deferred_lets_must_be_assigned_on_every_path() {
+        assert!(errors("fn f(c: bool): i32 { let x; if c { x = 1; } else if !c { x = 2; } else { return 0; } x }").is_empty());
+        assert!(errors("fn f(c: bool): i32 { let x: i32; if c { if !c { x = 1; } } else { x = 2; } 0 }").is_empty());
+        let possibly = ["used binding `x` is possibly-uninitialized"];
+        assert_eq!(errors("fn f(c: bool): i32 { let x: i32; if c { x = 1; } x }"), possibly);
+        assert_eq!(errors("fn f(c: bool): i32 { let x: i32; if c { x = 1; } else if !c { x = 2; } x }"), possibly);
+        assert_eq!(errors("fn f(c: bool): i32 { let x: i32; if c { if !c { x = 1; } } else { x = 2; } x }"), possibly);
+        assert_eq!(errors("enum E { A, B } fn f(e: E): i32 { let x: i32; match e { E::A => { x = 1; } E::B => {} } x }"), possibly);
+        assert_eq!(errors("fn f(c: bool): i32 { let mut x: i32; while c { x = 1; } x }"), possibly);
+        assert_eq!(errors("fn f(): i32 { let x: i32; x = x + 1; x }"), ["used binding `x` isn't initialized"]);
+        // an assignment after one that only maybe happened is still a second one
+        assert_eq!(
+            errors("fn f(c: bool): i32 { let x: i32; if c { x = 1; } x = 2; x }"),
+            ["cannot assign twice to immutable variable `x`"],
+        );
+    }
+
+    #[test]
+    fn a_deferred_let_assigned_in_a_loop_points_at_the_assignment() {
+        let diagnostics = check_src("fn f(c: bool) {\n    let x: i32;\n    while c { x = 1; }\n}");
+        let [diagnostic] = diagnostics.as_slice() else {
+            panic!("expected one diagnostic, found {:?}", diagnostics);
+        };
+        assert_eq!(diagnostic.msg, "cannot assign twice to immutable variable `x`");
+        let labels = diagnostic.labels.iter().map(|l| (l.span.line, l.span.col, l.msg.as_str())).collect::<Vec<_>>();
+        assert_eq!(labels, [
+            (3, 15, "first assigned here, then again by the next iteration of the loop"),
+            (2, 5, "`x` declared here"),
+        ]);
+    }
+
+    #[test]
+    fn functions_must_return_on_every_path() {
+        assert!(errors("fn f(a: i32): i32 { if a > 0 { return 1; } else { return 2; } }").is_empty());
+        assert!(errors("fn f(a: i32): i32 { while a > 0 { return 1; } return 0; }").is_empty());
+        assert!(errors("fn f(a: i32): i32 { if a > 0 { return 1; } a }").is_empty());
+        assert_eq!(errors("fn f(a: i32): i32 { if a > 0 { return 1; } }"), ["mismatched types"]);
+        assert_eq!(errors("fn f(a: i32): i32 { while a > 0 { return 1; } }"), ["mismatched types"]);
+        assert_eq!(errors("fn f() { return 1; }"), ["mismatched types"]);
+        assert_eq!(errors("fn f(): i32 { return; }"), ["`return;` in a function that returns a value"]);
+    }
+
+    #[test]
+    fn blocks_have_the_value_they_end_in() {
+        assert!(errors("fn f(): i64 { let b = { 0 }; let c: i64 = { let d = 2; d * b }; c }").is_empty());
+        assert!(errors("fn f(a: i32): i32 { { if a > 0 { return 1; } 2 } }").is_empty());
+        assert!(errors("fn f(a: i32): i32 { { return a; } }").is_empty());
+        assert_eq!(errors("fn f(): i32 { { 1.5 } }"), ["mismatched types"]);
+        assert_eq!(errors("fn f(): i32 { { 1; } }"), ["mismatched types"]);
+        assert_eq!(errors("fn f(): u8 { let b: u8 = { 300 }; b }"), ["literal out of range for `u8`"]);
+    }
+
+    #[test]
+    fn fields_are_assigned_through_mutable_bindings() {
+        let src = |body: &str| format!("struct P {{ x: i32 }} fn f(p: P) {{ {} }}", body);
+        // a struct is copied when it is bound, so a mutable copy can be changed without changing `p`
+        assert!(errors(&src("let mut q = p; q.x = 1;")).is_empty());
+        assert_eq!(errors(&src("let q = p; q.x = 1;")), ["cannot assign to `q.x`, as `q` is not declared as mutable"]);
+        assert_eq!(errors(&src("p.x = 1;")), ["cannot assign to `p.x`, as `p` is not declared as mutable"]);
+    }
+
+    #[test]
+    fn conditions_must_be_bool() {
+        assert!(errors("fn f(a: i32, b: bool): bool { if a > 0 && !b || b == false { true } else { a != 0 } }").is_empty());
+        assert_eq!(errors("fn f(a: i32) { if a { } }"), ["mismatched types"]);
+        assert_eq!(errors("fn f(a: i32) { while !a { } }"), ["cannot apply `!` to `i32`"]);
+        assert_eq!(errors("fn f(a: bool): bool { a || 1 }"), ["mismatched types"]);
+    }
+
+    #[test]
+    fn casts_convert_between_numbers() {
+        assert!(errors("fn f(a: u8): f64 { let b = a as i64; b as f64 * 1.5 }").is_empty());
+        assert_eq!(errors("fn f(): f32 { 1.5 as f64 }"), ["mismatched types"]);
+        assert_eq!(errors(r#"fn f(): i32 { "a" as i32 }"#), ["non-primitive cast: `String` as `i32`"]);
+    }
+
+    #[test]
+    fn literals_must_fit_their_type() {
+        assert!(errors("fn f(): u64 { let a: u8 = 255; let b = 10i64 - 1; 18_446_744_073_709_551_615 }").is_empty());
+        assert_eq!(errors("fn f(): u8 { -1 }"), ["literal out of range for `u8`"]);
+        assert_eq!(errors("fn f(): i32 { 2 ^ 31 }"), ["this arithmetic operation will overflow"]);
+        assert!(errors("fn f(): i32 { 2 ^ 30 - 1 + 2 ^ 30 }").is_empty());
+    }
+
+    #[test]
+    fn numbers_widen_where_a_type_is_expected() {
+        assert!(errors("fn f(a: u8, b: i32, c: f32): i64 { let d: f64 = c; let e: u32 = a; b }").is_empty());
+        assert_eq!(errors("fn f(a: i8): u64 { a }"), ["mismatched types"]);
+        assert_eq!(errors("fn f(a: i64): i32 { a }"), ["mismatched types"]);
+        assert_eq!(errors("fn f(a: i32, b: i64): i64 { a + b }"), ["mismatched types `i32` and `i64`"]);
+    }
+
+    #[test]
+    fn chars_compare_cast_and_go_into_strings() {
+        assert!(errors(r#"fn f(a: u8): bool { let c = a as char; let s = "ab" + c; s[1] as u32 > 97 && c <= 'z' }"#).is_empty());
+        assert_eq!(errors("fn f(a: u32): char { a as char }"), ["only `u8` can be cast as `char`, not `u32`"]);
+        assert_eq!(errors("fn f(): char { 'a' + 'b' }"), ["cannot apply `+` to `char`"]);
+        assert_eq!(errors(r#"fn f(): char { "ab"['a'] }"#), ["mismatched types"]);
+        assert_eq!(errors(r#"fn f() { let s = "ab" + 1; }"#), ["mismatched types"]);
+        assert_eq!(errors("fn f(): char { 'a'[0] }"), ["cannot index into a value of type `char`"]);
+    }
+
+    /// a function matching a `Shape` with the given arms
+    fn shape_match(arms: &str) -> String {
+        format!(r#"
+enum Shape {{
+    Circle(f32),
+    Rect {{ w: i32, h: i32 }},
+    Empty,
+}}
+
+fn area(s: Shape): i32 {{
+    match s {{
+        {}
+    }}
+}}
+"#, arms)
+    }
+
+    #[test]
+    fn match_must_cover_every_variant() {
+        assert_eq!(
+            errors(&shape_match("Shape::Circle(_) => 1, Shape::Empty => 0,")),
+            ["non-exhaustive patterns: `Shape::Rect { .. }` not covered"],
+        );
+        assert!(check_src(&shape_match("Shape::Rect { w, h } => w * h, _ => 0,")).is_empty());
+    }
+
+    #[test]
+    fn match_warns_about_unreachable_arms() {
+        let diagnostics = check_src(&shape_match("Shape::Rect { w, .. } => w, _ => 0, Shape::Empty => 1,"));
+        assert_eq!(diagnostics.len(), 1);
+        assert!(!diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].msg, "unreachable pattern");
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 
 /// super fucking hacky. not prod ready lol
@@ -29,3 +29,38 @@ pub fn get_exports(input: String) -> HashSet<(String, String)> {
 
     result
 }
+
+/// the param and result types of every function in a wat module, keyed by the
+/// function id without the `$`. only looks at signatures written on the same
+/// line as the `(func`
+pub fn get_signatures(input: &str) -> HashMap<String, (Vec<String>, Option<String>)> {
+    let mut result = HashMap::new();
+    for line in input.lines() {
+        // (export "x" (func $x)) only refers to a function
+        if line.trim_start().starts_with("(export") {
+            continue;
+        }
+        let Some(start) = line.find("(func $") else {
+            continue;
+        };
+        let line = &line[start + 7..];
+        let name = line
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != ')')
+            .collect::<String>();
+        let mut params = Vec::new();
+        let mut ret = None;
+        for (i, _) in line.match_indices('(') {
+            let group = line[i + 1..].split(')').next().unwrap_or("");
+            let mut words = group.split_whitespace();
+            match words.next() {
+                // (param $name i32) or (param i32 i32)
+                Some("param") => params.extend(words.filter(|w| !w.starts_with('$')).map(String::from)),
+                Some("result") => ret = words.next().map(String::from),
+                _ => {}
+            }
+        }
+        result.insert(name, (params, ret));
+    }
+    result
+}
@@ -20,8 +20,7 @@
         (i32.add)
         (local.set $ptr)
         ;; store a pointer to the first byte in magic address 100
-        ;;(i32.store (i32.const 100) (local.get $ptr))
-        (i32.store (i32.const 100) (i32.const 4))
+        (i32.store (i32.const 100) (local.get $ptr))
 
         (call $fd_write
             (i32.const 1) ;; file_descriptor - 1 for stdout
@@ -31,6 +30,125 @@
         )
         drop
     )
+    ;; writes `len` bytes starting at `ptr` to stdout
+    (func $__write (param $ptr i32) (param $len i32)
+        (i32.store (i32.const 100) (local.get $ptr))
+        (i32.store (i32.const 104) (local.get $len))
+        (call $fd_write (i32.const 1) (i32.const 100) (i32.const 1) (i32.const 20))
+        drop
+    )
+
+    ;; writes the digits of `n` into the bytes just before `end`, returning the address of the first one
+    (func $__fmt_u64 (param $n i64) (param $end i32) (result i32)
+        (loop $digit
+            (local.set $end (i32.sub (local.get $end) (i32.const 1)))
+            (i32.store8 (local.get $end)
+                (i32.add (i32.const 48) (i32.wrap_i64 (i64.rem_u (local.get $n) (i64.const 10)))))
+            (local.set $n (i64.div_u (local.get $n) (i64.const 10)))
+            (br_if $digit (i64.ne (local.get $n) (i64.const 0)))
+        )
+        (local.get $end)
+    )
+
+    ;; prints a float with at most 6 digits after the point, like `1.5`, `-0.333333` or `2`.
+    ;; the digits are built up backwards in the bytes before address 1000, just below the heap
+    (func $print_f64 (param $x f64)
+        (local $int i64)
+        (local $frac i64)
+        (local $neg i32)
+        (local $start i32)
+        (local $len i32)
+        (local $i i32)
+        ;; NaN is the only value that isn't equal to itself
+        (if (f64.ne (local.get $x) (local.get $x))
+            (then
+                (i32.store (i32.const 960) (i32.const 0x4e614e)) ;; "NaN"
+                (call $__write (i32.const 960) (i32.const 3))
+                (return)))
+        (local.set $neg (i64.lt_s (i64.reinterpret_f64 (local.get $x)) (i64.const 0)))
+        (local.set $x (f64.abs (local.get $x)))
+        (if (f64.eq (local.get $x) (f64.const inf))
+            (then
+                (i32.store (i32.const 960) (i32.const 0x666e692d)) ;; "-inf"
+                (call $__write
+                    (i32.sub (i32.const 961) (local.get $neg))
+                    (i32.add (i32.const 3) (local.get $neg)))
+                (return)))
+
+        ;; from 2^64 on the integer part doesn't fit in an i64, so these are
+        ;; printed as `{x}e{exponent}` with 1 <= x < 10
+        (if (f64.ge (local.get $x) (f64.const 18446744073709551616))
+            (then
+                (local.set $i (i32.const 0))
+                (loop $shift
+                    (local.set $x (f64.div (local.get $x) (f64.const 10)))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $shift (f64.ge (local.get $x) (f64.const 10))))
+                ;; 9.9999999 rounds up to 10 when printed
+                (if (f64.ge (local.get $x) (f64.const 9.9999995))
+                    (then
+                        (local.set $x (f64.div (local.get $x) (f64.const 10)))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))))
+                (if (local.get $neg)
+                    (then (local.set $x (f64.neg (local.get $x)))))
+                (call $print_f64 (local.get $x))
+                (i32.store8 (i32.const 960) (i32.const 101)) ;; "e"
+                (call $__write (i32.const 960) (i32.const 1))
+                (local.set $start (call $__fmt_u64 (i64.extend_i32_u (local.get $i)) (i32.const 1000)))
+                (call $__write (local.get $start) (i32.sub (i32.const 1000) (local.get $start)))
+                (return)))
+
+        (local.set $int (i64.trunc_sat_f64_u (local.get $x)))
+        (local.set $frac (i64.trunc_sat_f64_u (f64.nearest
+            (f64.mul (f64.sub (local.get $x) (f64.convert_i64_u (local.get $int))) (f64.const 1000000)))))
+        ;; 0.9999999 rounds up to the next integer
+        (if (i64.eq (local.get $frac) (i64.const 1000000))
+            (then
+                (local.set $int (i64.add (local.get $int) (i64.const 1)))
+                (local.set $frac (i64.const 0))))
+
+        ;; the 6 digits after the point go in 994..1000, without their trailing zeros
+        (local.set $i (i32.const 1000))
+        (loop $digit
+            (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+            (i32.store8 (local.get $i)
+                (i32.add (i32.const 48) (i32.wrap_i64 (i64.rem_u (local.get $frac) (i64.const 10)))))
+            (local.set $frac (i64.div_u (local.get $frac) (i64.const 10)))
+            (br_if $digit (i32.gt_u (local.get $i) (i32.const 994)))
+        )
+        (local.set $len (i32.const 6))
+        (block $trimmed
+            (loop $trim
+                (br_if $trimmed (i32.eqz (local.get $len)))
+                (br_if $trimmed (i32.ne (i32.load8_u (i32.add (i32.const 993) (local.get $len))) (i32.const 48)))
+                (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+                (br $trim)
+            )
+        )
+        (local.set $start (i32.const 994))
+        (if (local.get $len)
+            (then
+                (local.set $start (i32.const 993))
+                (i32.store8 (local.get $start) (i32.const 46)) ;; "."
+                (local.set $len (i32.add (local.get $len) (i32.const 1)))))
+
+        (local.set $i (call $__fmt_u64 (local.get $int) (local.get $start)))
+        (local.set $len (i32.add (local.get $len) (i32.sub (local.get $start) (local.get $i))))
+        (local.set $start (local.get $i))
+        (if (local.get $neg)
+            (then
+                (local.set $start (i32.sub (local.get $start) (i32.const 1)))
+                (i32.store8 (local.get $start) (i32.const 45)) ;; "-"
+                (local.set $len (i32.add (local.get $len) (i32.const 1)))))
+        (call $__write (local.get $start) (local.get $len))
+    )
+
+    (func $print_f32 (param $x f32)
+        (call $print_f64 (f64.promote_f32 (local.get $x)))
+    )
+
     (export "println" (func $println))
+    (export "print_f32" (func $print_f32))
+    (export "print_f64" (func $print_f64))
 )
 